@@ -0,0 +1,148 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use day_05::{
+    Crate, Instruction, InstructionApplicationType, MoveInstruction, Piles, Stack, Stacks,
+};
+
+// xorshift, good enough to shuffle crates around and keeps the
+// generated inputs the same from run to run
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+// generates piles and a program that never moves more crates than a
+// pile holds, so it can be run with any of the appliers
+fn generate(
+    n_piles: usize,
+    crates_per_pile: usize,
+    n_instructions: usize,
+    max_quantity: usize,
+) -> (Piles, Vec<Instruction>) {
+    let mut rng = Rng(0x2022_0005);
+
+    let piles = Piles(
+        (0..n_piles)
+            .map(|_| {
+                (0..crates_per_pile)
+                    .map(|_| Crate::from((b'A' + rng.below(26) as u8) as char))
+                    .collect()
+            })
+            .collect(),
+    );
+
+    let mut heights: Vec<usize> = vec![crates_per_pile; n_piles];
+    let instructions = (0..n_instructions)
+        .map(|_| {
+            let src = loop {
+                let src = rng.below(n_piles);
+                if heights[src] > 0 {
+                    break src;
+                }
+            };
+            let dst = (src + 1 + rng.below(n_piles - 1)) % n_piles;
+            let quantity = 1 + rng.below(max_quantity.min(heights[src]));
+
+            heights[src] -= quantity;
+            heights[dst] += quantity;
+
+            Instruction { quantity, src, dst }
+        })
+        .collect();
+
+    (piles, instructions)
+}
+
+fn to_stacks(piles: &Piles) -> Stacks {
+    Stacks {
+        stacks: piles
+            .0
+            .iter()
+            .enumerate()
+            .map(|(i, pile)| Stack {
+                name: i + 1,
+                stack: pile
+                    .iter()
+                    .flat_map(|krate| krate.label().chars())
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+fn to_move_instruction(instruction: &Instruction) -> MoveInstruction {
+    MoveInstruction {
+        count: instruction.quantity,
+        from: instruction.src + 1,
+        to: instruction.dst + 1,
+    }
+}
+
+fn bench_moves(c: &mut Criterion) {
+    // (name, max quantity per move)
+    for (name, max_quantity) in [("small moves", 3), ("large moves", 500)] {
+        let (piles, instructions) = generate(9, 2_000, 50_000, max_quantity);
+        let stacks = to_stacks(&piles);
+        let move_instructions: Vec<_> = instructions.iter().map(to_move_instruction).collect();
+
+        let mut group = c.benchmark_group(name);
+        group.sample_size(20);
+
+        for iat in [
+            InstructionApplicationType::Type_9000,
+            InstructionApplicationType::Type_9000_drain,
+            InstructionApplicationType::Type_9001,
+            InstructionApplicationType::Type_9001_smallVec,
+            InstructionApplicationType::Type_9001_drain,
+        ] {
+            group.bench_function(BenchmarkId::new("Piles", format!("{iat:?}")), |b| {
+                b.iter_batched_ref(
+                    || piles.clone(),
+                    |piles| {
+                        for instruction in &instructions {
+                            piles.apply(instruction, iat);
+                        }
+                    },
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+
+        group.bench_function(BenchmarkId::new("Stacks", "CrateMover9000"), |b| {
+            b.iter_batched_ref(
+                || stacks.clone(),
+                |stacks| {
+                    for instruction in &move_instructions {
+                        stacks.apply_CrateMover9000(instruction);
+                    }
+                },
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_function(BenchmarkId::new("Stacks", "CrateMover9001"), |b| {
+            b.iter_batched_ref(
+                || stacks.clone(),
+                |stacks| {
+                    for instruction in &move_instructions {
+                        stacks.apply_CrateMover9001(instruction);
+                    }
+                },
+                BatchSize::LargeInput,
+            )
+        });
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_moves);
+criterion_main!(benches);
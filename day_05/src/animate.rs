@@ -0,0 +1,181 @@
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+
+use crate::replay::Replay;
+
+// ANSI escapes, written by hand like the rest of the drawing
+const CLEAR: &str = "\x1b[H\x1b[J";
+const MOVED: &str = "\x1b[1;32m";
+const RESET: &str = "\x1b[0m";
+const ENTER_ALTERNATE_SCREEN: &str = "\x1b[?1049h\x1b[?25l";
+const LEAVE_ALTERNATE_SCREEN: &str = "\x1b[?25h\x1b[?1049l";
+
+// `-` doubles the delay, so it has to stay above 0
+pub const MIN_DELAY: Duration = Duration::from_millis(1);
+const MAX_DELAY: Duration = Duration::from_secs(5);
+
+// Draws the current piles of the replay, with the crates moved by the last
+// instruction highlighted. In raw mode a `\n` doesn't go back to the start
+// of the line, so lines end with `\r\n`.
+pub fn frame(replay: &Replay, delay: Duration, paused: bool) -> String {
+    let piles = replay.piles();
+
+    // the moved crates are now on top of the destination pile
+    let moved = replay.last_instruction().map(|instruction| {
+        let top = piles.0[instruction.dst].len();
+        (
+            instruction.dst,
+            top.saturating_sub(instruction.quantity)..top,
+        )
+    });
+
+    let mut drawing = String::new();
+    piles
+        .write_drawing(&mut drawing, |pile, level, krate| match &moved {
+            Some((dst, levels)) if *dst == pile && levels.contains(&level) => {
+                format!("{MOVED}{krate}{RESET}")
+            }
+            _ => krate,
+        })
+        .expect("writing to a String does not fail");
+
+    let mut frame = String::from(CLEAR);
+    let status = match replay.last_instruction() {
+        Some(instruction) => format!("{}/{}: {instruction}", replay.position(), replay.len()),
+        None => format!("0/{}", replay.len()),
+    };
+    write!(frame, "{status}\r\n\r\n").unwrap();
+    for line in drawing.lines() {
+        write!(frame, "{line}\r\n").unwrap();
+    }
+    write!(
+        frame,
+        "\r\n{} every {}ms | space: pause, s/b: step forward/back, +/-: speed, q: quit\r\n",
+        if paused { "paused," } else { "running," },
+        delay.as_millis()
+    )
+    .unwrap();
+
+    frame
+}
+
+// puts the terminal back to normal, also when the animation panics
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        print!("{ENTER_ALTERNATE_SCREEN}");
+        Ok(RawTerminal)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        print!("{LEAVE_ALTERNATE_SCREEN}");
+        let _ = io::stdout().flush();
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// Plays the replay in the terminal, applying an instruction every `delay`
+// until the end of the program. Stops at the end instead of quitting, so the
+// final piles can be looked at.
+pub fn animate(replay: &mut Replay, mut delay: Duration) -> io::Result<()> {
+    let _terminal = RawTerminal::enter()?;
+    let mut stdout = io::stdout();
+    let mut paused = false;
+
+    loop {
+        paused |= replay.next_instruction().is_none();
+        stdout.write_all(frame(replay, delay, paused).as_bytes())?;
+        stdout.flush()?;
+
+        // while running, a key that doesn't arrive in time means it's time
+        // for the next instruction
+        if !paused && !event::poll(delay)? {
+            replay.step_forward();
+            continue;
+        }
+
+        let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event::read()?
+        else {
+            continue;
+        };
+
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => break,
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => break,
+            KeyCode::Char(' ') => paused = !paused,
+            KeyCode::Char('s') | KeyCode::Right => {
+                paused = true;
+                replay.step_forward();
+            }
+            KeyCode::Char('b') | KeyCode::Left => {
+                paused = true;
+                replay.step_back();
+            }
+            KeyCode::Char('+') => delay = (delay / 2).max(MIN_DELAY),
+            KeyCode::Char('-') => delay = (delay * 2).min(MAX_DELAY),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{frame, MOVED, RESET};
+    use crate::{replay::Replay, InstructionApplicationType};
+    use std::time::Duration;
+
+    const SAMPLE: &str = "    [D]
+[N] [C]
+[Z] [M] [P]
+ 1   2   3
+
+move 1 from 2 to 1
+move 3 from 1 to 3";
+
+    #[test]
+    fn test_frame_highlights_moved_crates() {
+        let mut replay = Replay::new(SAMPLE, InstructionApplicationType::Type_9001).unwrap();
+
+        let before = frame(&replay, Duration::from_millis(100), false);
+        assert!(!before.contains(MOVED));
+
+        replay.step_forward();
+        replay.step_forward();
+        let after = frame(&replay, Duration::from_millis(100), true);
+
+        assert!(after.contains("2/2: move 3 from 1 to 3"));
+        assert!(after.contains(&format!("{MOVED}[D]{RESET}")));
+        assert!(after.contains(&format!("{MOVED}[N]{RESET}")));
+        assert!(after.contains(&format!("{MOVED}[Z]{RESET}")));
+        assert!(!after.contains(&format!("{MOVED}[P]{RESET}")));
+        assert!(after.contains("paused"));
+    }
+
+    #[test]
+    fn test_frame_without_escapes_is_the_drawing() {
+        let mut replay = Replay::new(SAMPLE, InstructionApplicationType::Type_9000).unwrap();
+        replay.step_forward();
+
+        let drawing = frame(&replay, Duration::from_millis(100), false)
+            .replace(MOVED, "")
+            .replace(RESET, "")
+            .replace("\r\n", "\n");
+
+        assert!(drawing.contains(&replay.piles().to_string()));
+    }
+}
@@ -0,0 +1,371 @@
+use miette::SourceSpan;
+use nom::{
+    bytes::complete::tag,
+    character::complete::{self as cc, line_ending, not_line_ending},
+    combinator::{all_consuming, consumed, opt},
+    sequence::{preceded, terminated, tuple},
+    Finish, IResult,
+};
+use nom_locate::LocatedSpan;
+use nom_supreme::error::{BaseErrorKind, ErrorTree, GenericErrorTree};
+
+use crate::drawing::{parse_drawing, DrawingError, DrawingErrorKind};
+use crate::{Instruction, InstructionApplicationType, Piles};
+
+pub type Span<'a> = LocatedSpan<&'a str>;
+
+type ErrorKind = BaseErrorKind<&'static str, Box<dyn std::error::Error + Send + Sync>>;
+
+#[derive(thiserror::Error, Debug, miette::Diagnostic)]
+pub enum CheckError {
+    #[error("{line}:{column}: malformed crate drawing")]
+    BadDrawing {
+        line: u32,
+        column: usize,
+
+        #[label("{kind}")]
+        bad_part: SourceSpan,
+
+        kind: DrawingErrorKind,
+    },
+
+    #[error("{line}:{column}: malformed instruction")]
+    Malformed {
+        line: u32,
+        column: usize,
+
+        #[label("{kind}")]
+        bad_part: SourceSpan,
+
+        kind: ErrorKind,
+    },
+
+    #[error("{line}:{column}: pile {pile} does not exist, piles are numbered 1 to {num_piles}")]
+    PileOutOfRange {
+        line: u32,
+        column: usize,
+
+        #[label("no such pile")]
+        bad_part: SourceSpan,
+
+        pile: usize,
+        num_piles: usize,
+    },
+
+    #[error("{line}:{column}: cannot move {quantity} crates from pile {pile}, it only holds {available}")]
+    NotEnoughCrates {
+        line: u32,
+        column: usize,
+
+        #[label("only {available} crates available")]
+        bad_part: SourceSpan,
+
+        quantity: usize,
+        pile: usize,
+        available: usize,
+    },
+}
+
+// all the errors of a checked run, rendered against the whole input
+#[derive(thiserror::Error, Debug, miette::Diagnostic)]
+#[error("found {} bad instructions", errors.len())]
+pub struct BadInstructions {
+    #[source_code]
+    pub src: String,
+
+    #[related]
+    pub errors: Vec<CheckError>,
+}
+
+#[derive(Debug)]
+pub struct CheckedRun {
+    pub piles: Piles,
+    pub n_applied: usize,
+    pub errors: Vec<CheckError>,
+}
+
+// an instruction as written in the input: 1-based pile numbers, each
+// number kept together with the span it was parsed from
+struct LocatedInstruction<'a> {
+    quantity: (Span<'a>, usize),
+    src: (Span<'a>, usize),
+    dst: (Span<'a>, usize),
+}
+
+fn parse_located_number(i: Span) -> IResult<Span, (Span, usize), ErrorTree<Span>> {
+    let (i, (span, n)) = consumed(cc::u64)(i)?;
+    Ok((i, (span, n as usize)))
+}
+
+fn parse_located_instruction(i: Span) -> IResult<Span, LocatedInstruction, ErrorTree<Span>> {
+    let (i, (quantity, src, dst)) = tuple((
+        preceded(tag("move "), parse_located_number),
+        preceded(tag(" from "), parse_located_number),
+        preceded(tag(" to "), parse_located_number),
+    ))(i)?;
+
+    Ok((i, LocatedInstruction { quantity, src, dst }))
+}
+
+// splits the input into lines while keeping their location
+fn parse_lines(i: Span) -> IResult<Span, Vec<Span>, ErrorTree<Span>> {
+    let mut lines = vec![];
+    let mut i = i;
+
+    while !i.fragment().is_empty() {
+        let (next_i, line) = terminated(not_line_ending, opt(line_ending))(i)?;
+        lines.push(line);
+        i = next_i;
+    }
+
+    Ok((i, lines))
+}
+
+fn location(span: Span) -> (u32, usize, SourceSpan) {
+    (
+        span.location_line(),
+        span.get_utf8_column(),
+        SourceSpan::new(span.location_offset().into(), span.fragment().len().into()),
+    )
+}
+
+// `Stack` and `Alt` errors wrap the actual failures, this picks the
+// one that got the furthest into the line
+fn deepest_base_error(e: ErrorTree<Span>) -> (Span, ErrorKind) {
+    match e {
+        GenericErrorTree::Base { location, kind } => (location, kind),
+        GenericErrorTree::Stack { base, .. } => deepest_base_error(*base),
+        GenericErrorTree::Alt(alternatives) => alternatives
+            .into_iter()
+            .map(deepest_base_error)
+            .max_by_key(|(location, _)| location.location_offset())
+            .expect("alt errors always have at least one alternative"),
+    }
+}
+
+fn bad_drawing(input: &str, drawing: &[Span], e: DrawingError) -> CheckError {
+    // `DrawingError` counts lines from the start of the drawing, which is
+    // also the start of the input
+    let bad_part = match drawing.get(e.line - 1) {
+        Some(line) => {
+            let mut chars = line.fragment().char_indices().skip(e.column - 1);
+            match chars.next() {
+                Some((offset, c)) => SourceSpan::new(
+                    (line.location_offset() + offset).into(),
+                    c.len_utf8().into(),
+                ),
+                None => SourceSpan::new(
+                    (line.location_offset() + line.fragment().len()).into(),
+                    0.into(),
+                ),
+            }
+        }
+        None => SourceSpan::new(input.len().into(), 0.into()),
+    };
+
+    CheckError::BadDrawing {
+        line: e.line as u32,
+        column: e.column,
+        bad_part,
+        kind: e.kind,
+    }
+}
+
+fn check_pile_number(
+    (span, pile): (Span, usize),
+    num_piles: usize,
+    errors: &mut Vec<CheckError>,
+) -> Option<usize> {
+    if (1..=num_piles).contains(&pile) {
+        return Some(pile - 1);
+    }
+
+    let (line, column, bad_part) = location(span);
+    errors.push(CheckError::PileOutOfRange {
+        line,
+        column,
+        bad_part,
+        pile,
+        num_piles,
+    });
+
+    None
+}
+
+fn check_instruction(
+    piles: &Piles,
+    instruction: &LocatedInstruction,
+    errors: &mut Vec<CheckError>,
+) -> Option<Instruction> {
+    let num_piles = piles.0.len();
+
+    // check both pile numbers before bailing out, so both get reported
+    let src = check_pile_number(instruction.src, num_piles, errors);
+    let dst = check_pile_number(instruction.dst, num_piles, errors);
+    let (src, dst) = (src?, dst?);
+
+    let (quantity_span, quantity) = instruction.quantity;
+    let available = piles.0[src].len();
+    if quantity > available {
+        let (line, column, bad_part) = location(quantity_span);
+        errors.push(CheckError::NotEnoughCrates {
+            line,
+            column,
+            bad_part,
+            quantity,
+            pile: instruction.src.1,
+            available,
+        });
+
+        return None;
+    }
+
+    Some(Instruction { quantity, src, dst })
+}
+
+// like `functional_style`, but never panics on bad input: every
+// instruction that can't be parsed or applied is reported and skipped,
+// and the remaining instructions are still applied.
+pub fn run_checked(input: &str, iat: InstructionApplicationType) -> Result<CheckedRun, CheckError> {
+    let (_, lines) = parse_lines(Span::new(input))
+        .finish()
+        .expect("splitting into lines never fails");
+    let mut lines = lines.into_iter();
+
+    // everything up to the first empty line is the crate drawing
+    let drawing: Vec<Span> = (&mut lines)
+        .take_while(|line| !line.fragment().is_empty())
+        .collect();
+    let fragments: Vec<&str> = drawing.iter().map(|line| *line.fragment()).collect();
+    let mut piles = parse_drawing(&fragments).map_err(|e| bad_drawing(input, &drawing, e))?;
+    let mut n_applied = 0;
+    let mut errors = vec![];
+
+    for line in lines {
+        let located = match all_consuming(parse_located_instruction)(line).finish() {
+            Ok((_, located)) => located,
+            Err(e) => {
+                let (span, kind) = deepest_base_error(e);
+                // the error location spans the rest of the line, only
+                // point at where it starts
+                let (line, column, _) = location(span);
+                let bad_part = SourceSpan::new(span.location_offset().into(), 0.into());
+                errors.push(CheckError::Malformed {
+                    line,
+                    column,
+                    bad_part,
+                    kind,
+                });
+                continue;
+            }
+        };
+
+        if let Some(instruction) = check_instruction(&piles, &located, &mut errors) {
+            piles.apply(&instruction, iat);
+            n_applied += 1;
+        }
+    }
+
+    Ok(CheckedRun {
+        piles,
+        n_applied,
+        errors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_checked, CheckError};
+    use crate::InstructionApplicationType;
+    use test_case::test_case;
+
+    const DRAWING: &str = "    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 
+
+";
+
+    #[test]
+    fn test_run_checked_sample() {
+        let input = format!(
+            "{DRAWING}move 1 from 2 to 1\nmove 3 from 1 to 3\nmove 2 from 2 to 1\nmove 1 from 1 to 2"
+        );
+
+        let run = run_checked(&input, InstructionApplicationType::Type_9000).unwrap();
+
+        assert!(run.errors.is_empty());
+        assert_eq!(4, run.n_applied);
+        assert_eq!("CMZ", run.piles.tops());
+    }
+
+    #[test_case("move 1 from 0 to 1", 6, 13)]
+    #[test_case("move 1 from 4 to 1", 6, 13)]
+    #[test_case("move 1 from 1 to 12", 6, 18)]
+    fn test_pile_out_of_range(instruction: &str, line: u32, column: usize) {
+        let input = format!("{DRAWING}{instruction}");
+
+        let run = run_checked(&input, InstructionApplicationType::Type_9000).unwrap();
+
+        assert_eq!(0, run.n_applied);
+        assert!(matches!(
+            run.errors[..],
+            [CheckError::PileOutOfRange { line: l, column: c, num_piles: 3, .. }] if (l, c) == (line, column)
+        ));
+    }
+
+    #[test_case("move 1 from 2 to 1\nmove 4 from 1 to 3", 7, 6, 3)]
+    #[test_case("move 4 from 3 to 1", 6, 6, 1)]
+    fn test_not_enough_crates(instructions: &str, line: u32, column: usize, available: usize) {
+        let input = format!("{DRAWING}{instructions}");
+
+        let run = run_checked(&input, InstructionApplicationType::Type_9001).unwrap();
+
+        assert!(matches!(
+            run.errors[..],
+            [CheckError::NotEnoughCrates { line: l, column: c, available: a, .. }] if (l, c, a) == (line, column, available)
+        ));
+    }
+
+    #[test]
+    fn test_errors_are_collected() {
+        let input = format!(
+            "{DRAWING}move 1 from 2 to 1\nmove one from 1 to 3\nmove 1 from 0 to 4\nmove 1 from 1 to 2"
+        );
+
+        let run = run_checked(&input, InstructionApplicationType::Type_9000).unwrap();
+
+        assert_eq!(2, run.n_applied);
+        assert!(matches!(
+            run.errors[..],
+            [
+                CheckError::Malformed {
+                    line: 7,
+                    column: 6,
+                    ..
+                },
+                CheckError::PileOutOfRange {
+                    line: 8,
+                    column: 13,
+                    pile: 0,
+                    ..
+                },
+                CheckError::PileOutOfRange {
+                    line: 8,
+                    column: 18,
+                    pile: 4,
+                    ..
+                },
+            ]
+        ));
+    }
+
+    #[test_case("[A] [B\n 1   2 \n\nmove 1 from 1 to 2", 1)]
+    #[test_case("[A] [B]\n 1   2 \nmove 1 from 1 to 2", 3)]
+    #[test_case("[A] [B]\n[C]   [D]\n 1   2 \n\n", 2)]
+    fn test_bad_drawing(input: &str, line: u32) {
+        let err = run_checked(input, InstructionApplicationType::Type_9000).unwrap_err();
+
+        assert!(matches!(err, CheckError::BadDrawing { line: l, .. } if l == line));
+    }
+}
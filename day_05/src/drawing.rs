@@ -0,0 +1,266 @@
+use crate::{Crate, Piles};
+
+// editors that insert tabs mostly do it every 4 columns, which is also
+// how wide a pile is in the puzzle drawings
+const TAB_WIDTH: usize = 4;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("{line}:{column}: {kind}")]
+pub struct DrawingError {
+    // 1-based, like in an editor. The column counts characters of the
+    // line as written, before expanding tabs.
+    pub line: usize,
+    pub column: usize,
+    pub kind: DrawingErrorKind,
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum DrawingErrorKind {
+    #[error("the drawing is empty")]
+    Empty,
+
+    #[error("expected a pile number, got {0:?}")]
+    BadPileNumber(String),
+
+    #[error("expected pile number {expected}, got {found}")]
+    UnexpectedPileNumber { expected: usize, found: usize },
+
+    #[error("expected a crate like `[A]`, got {0:?}")]
+    BadCrate(String),
+
+    #[error("crate label {0:?} is longer than {} bytes", Crate::MAX_LEN)]
+    LabelTooLong(String),
+
+    #[error("crate is not above exactly one pile number")]
+    Misaligned,
+
+    #[error("there is already a crate on pile {0} in this line")]
+    SamePile(usize),
+
+    #[error("crate on pile {0} has nothing below it")]
+    Floating(usize),
+}
+
+// something found in a line of the drawing, with the columns it spans
+// once tabs are expanded: `start..end`
+struct Token<'a> {
+    text: &'a str,
+    start: usize,
+    end: usize,
+    // 1-based, for error messages
+    column: usize,
+}
+
+// splits a line into whitespace separated tokens, keeping track of where
+// they are in the drawing
+fn tokenize(line: &str) -> Vec<Token<'_>> {
+    let mut tokens: Vec<Token> = vec![];
+    let mut expanded_column = 0;
+    let mut token_start = None;
+
+    let mut chars = line.char_indices().enumerate().peekable();
+    while let Some((column, (offset, c))) = chars.next() {
+        if !c.is_whitespace() {
+            if token_start.is_none() {
+                token_start = Some((column, offset, expanded_column));
+            }
+        } else if let Some((column, start_offset, start)) = token_start.take() {
+            tokens.push(Token {
+                text: &line[start_offset..offset],
+                start,
+                end: expanded_column,
+                column: column + 1,
+            });
+        }
+
+        expanded_column = match c {
+            '\t' => (expanded_column / TAB_WIDTH + 1) * TAB_WIDTH,
+            _ => expanded_column + 1,
+        };
+
+        if chars.peek().is_none() {
+            if let Some((column, start_offset, start)) = token_start.take() {
+                tokens.push(Token {
+                    text: &line[start_offset..],
+                    start,
+                    end: expanded_column,
+                    column: column + 1,
+                });
+            }
+        }
+    }
+
+    tokens
+}
+
+fn parse_crate_token(token: &Token) -> Result<Crate, DrawingErrorKind> {
+    let label = token
+        .text
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .filter(|label| !label.is_empty() && !label.contains(['[', ']']))
+        .ok_or_else(|| DrawingErrorKind::BadCrate(token.text.to_string()))?;
+
+    Crate::new(label).ok_or_else(|| DrawingErrorKind::LabelTooLong(label.to_string()))
+}
+
+// Parses the crate drawing of the puzzle, given all its lines with the
+// pile numbers last. Unlike `parse_crate_line`, crates are matched to the
+// pile number they are above instead of by counting characters, which
+// allows for:
+//
+// - 10 or more piles, with pile numbers of several digits
+// - crate labels of any length, like `[AB]`
+// - lines with trailing whitespace removed, and tabs
+pub fn parse_drawing(lines: &[&str]) -> Result<Piles, DrawingError> {
+    let Some((footer, crate_lines)) = lines.split_last() else {
+        return Err(DrawingError {
+            line: 1,
+            column: 1,
+            kind: DrawingErrorKind::Empty,
+        });
+    };
+
+    let footer_line = lines.len();
+    let pile_numbers = tokenize(footer);
+    if pile_numbers.is_empty() {
+        return Err(DrawingError {
+            line: footer_line,
+            column: 1,
+            kind: DrawingErrorKind::BadPileNumber(footer.to_string()),
+        });
+    }
+    for (i, token) in pile_numbers.iter().enumerate() {
+        let error = |kind| DrawingError {
+            line: footer_line,
+            column: token.column,
+            kind,
+        };
+
+        let number = token
+            .text
+            .parse::<usize>()
+            .map_err(|_| error(DrawingErrorKind::BadPileNumber(token.text.to_string())))?;
+        if number != i + 1 {
+            return Err(error(DrawingErrorKind::UnexpectedPileNumber {
+                expected: i + 1,
+                found: number,
+            }));
+        }
+    }
+
+    let mut piles: Vec<Vec<Crate>> = vec![vec![]; pile_numbers.len()];
+    // the drawing is read top to bottom, but the piles are built bottom up
+    for (line_index, line) in crate_lines.iter().enumerate().rev() {
+        let mut on_this_line = vec![false; pile_numbers.len()];
+
+        for token in tokenize(line) {
+            let error = |kind| DrawingError {
+                line: line_index + 1,
+                column: token.column,
+                kind,
+            };
+
+            let krate = parse_crate_token(&token).map_err(error)?;
+
+            let mut below = pile_numbers
+                .iter()
+                .enumerate()
+                .filter(|(_, number)| token.start < number.end && number.start < token.end)
+                .map(|(pile, _)| pile);
+            let pile = match (below.next(), below.next()) {
+                (Some(pile), None) => pile,
+                _ => return Err(error(DrawingErrorKind::Misaligned)),
+            };
+
+            if on_this_line[pile] {
+                return Err(error(DrawingErrorKind::SamePile(pile + 1)));
+            }
+            on_this_line[pile] = true;
+
+            // every line below this one has a crate on this pile, if not
+            // this one would be floating
+            if piles[pile].len() != crate_lines.len() - 1 - line_index {
+                return Err(error(DrawingErrorKind::Floating(pile + 1)));
+            }
+
+            piles[pile].push(krate);
+        }
+    }
+
+    Ok(Piles(piles))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_drawing, DrawingErrorKind};
+    use crate::{Crate, Piles};
+    use test_case::test_case;
+
+    #[test_case(&["    [D]    ", "[N] [C]    ", "[Z] [M] [P]", " 1   2   3 "]; "sample")]
+    #[test_case(&["    [D]", "[N] [C]", "[Z] [M] [P]", " 1   2   3"]; "trimmed lines")]
+    #[test_case(&["\t[D]", "[N]\t[C]", "[Z] [M]\t[P]", " 1\t 2\t 3"]; "tabs")]
+    fn test_parse_drawing(lines: &[&str]) {
+        let piles = parse_drawing(lines).unwrap();
+
+        assert_eq!(
+            "Pile 0: [Z, N]\nPile 1: [M, C, D]\nPile 2: [P]\n",
+            format!("{piles:?}")
+        );
+    }
+
+    #[test]
+    fn test_ten_piles_and_long_labels() {
+        let lines = [
+            "                                              [J]",
+            "[A]  [BC]           [DEF]                     [K]",
+            " 1    2    3    4    5    6    7    8    9    10 ",
+        ];
+
+        let piles = parse_drawing(&lines).unwrap();
+
+        assert_eq!(10, piles.0.len());
+        assert_eq!("ABCDEFJ", piles.tops());
+        assert_eq!(2, piles.0[9].len());
+    }
+
+    #[test]
+    fn test_render_round_trip() {
+        let piles = Piles(
+            (0..12)
+                .map(|i| {
+                    (0..i % 4)
+                        .map(|j| Crate::new(&"XYZW"[..=(i + j) % 4]).unwrap())
+                        .collect()
+                })
+                .collect(),
+        );
+
+        let rendered = piles.to_string();
+        let lines: Vec<_> = rendered.lines().collect();
+
+        assert_eq!(piles, parse_drawing(&lines).unwrap());
+    }
+
+    #[test_case(&[], 1, 1, DrawingErrorKind::Empty; "empty")]
+    #[test_case(&["[A]", " 1 ", ""], 3, 1, DrawingErrorKind::BadPileNumber(String::new()); "empty footer")]
+    #[test_case(&["[A] [B]", "[C]"], 2, 1, DrawingErrorKind::BadPileNumber("[C]".into()); "no footer")]
+    #[test_case(&["[A] [B]", " 1   3 "], 2, 6, DrawingErrorKind::UnexpectedPileNumber { expected: 2, found: 3 }; "pile numbers out of order")]
+    #[test_case(&["[A] [B", " 1   2 "], 1, 5, DrawingErrorKind::BadCrate("[B".into()); "unclosed crate")]
+    #[test_case(&["[A] B", " 1   2 "], 1, 5, DrawingErrorKind::BadCrate("B".into()); "not a crate")]
+    #[test_case(&["[A][B]", " 1   2 "], 1, 1, DrawingErrorKind::BadCrate("[A][B]".into()); "crates without space")]
+    #[test_case(&["[ABCDEFGH]", "    1     "], 1, 1, DrawingErrorKind::LabelTooLong("ABCDEFGH".into()); "label too long")]
+    #[test_case(&["[A]   [B]", " 1   2 "], 1, 7, DrawingErrorKind::Misaligned; "not above a pile")]
+    #[test_case(&["[ABCDE]", " 1   2 "], 1, 1, DrawingErrorKind::Misaligned; "above two piles")]
+    #[test_case(&["    [A]", "[B]", " 1   2 "], 1, 5, DrawingErrorKind::Floating(2); "floating crate")]
+    fn test_parse_drawing_errors(
+        lines: &[&str],
+        line: usize,
+        column: usize,
+        kind: DrawingErrorKind,
+    ) {
+        let err = parse_drawing(lines).unwrap_err();
+
+        assert_eq!((line, column, kind), (err.line, err.column, err.kind));
+    }
+}
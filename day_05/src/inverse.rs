@@ -0,0 +1,114 @@
+use crate::{Instruction, InstructionApplicationType, Piles};
+
+#[derive(thiserror::Error, Debug)]
+pub enum InverseError {
+    #[error("instruction {index} ({instruction}) can't be undone: pile {} does not exist", pile + 1)]
+    PileOutOfRange {
+        index: usize,
+        instruction: Instruction,
+        pile: usize,
+    },
+
+    #[error("instruction {index} ({instruction}) can't be undone: pile {} only holds {available} crates", instruction.dst + 1)]
+    NotEnoughCrates {
+        index: usize,
+        instruction: Instruction,
+        available: usize,
+    },
+
+    #[error(
+        "running the instructions on the recovered layout does not give back the final layout"
+    )]
+    Mismatch,
+}
+
+// walks the program backwards from the final layout, undoing every
+// instruction, see `Piles::undo`. The recovered layout is checked by
+// running the program forward again.
+pub fn recover_initial(
+    final_piles: &Piles,
+    instructions: &[Instruction],
+    iat: InstructionApplicationType,
+) -> Result<Piles, InverseError> {
+    let mut piles = final_piles.clone();
+
+    for (index, instruction) in instructions.iter().enumerate().rev() {
+        if let Some(&pile) = [instruction.src, instruction.dst]
+            .iter()
+            .find(|&&pile| pile >= piles.0.len())
+        {
+            return Err(InverseError::PileOutOfRange {
+                index,
+                instruction: instruction.clone(),
+                pile,
+            });
+        }
+
+        // undoing takes the crates back from the destination pile
+        let available = piles.0[instruction.dst].len();
+        if available < instruction.quantity {
+            return Err(InverseError::NotEnoughCrates {
+                index,
+                instruction: instruction.clone(),
+                available,
+            });
+        }
+
+        piles.undo(instruction, iat);
+    }
+
+    let mut check = piles.clone();
+    for instruction in instructions {
+        check.apply(instruction, iat);
+    }
+    if &check != final_piles {
+        return Err(InverseError::Mismatch);
+    }
+
+    Ok(piles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{recover_initial, InverseError};
+    use crate::{parse_piles, parse_puzzle, InstructionApplicationType};
+    use test_case::test_case;
+
+    #[test_case(InstructionApplicationType::Type_9000)]
+    #[test_case(InstructionApplicationType::Type_9001)]
+    fn test_recover_initial(iat: InstructionApplicationType) {
+        let (initial, instructions) = parse_puzzle(include_str!("../input.txt"));
+
+        let mut piles = initial.clone();
+        for instruction in &instructions {
+            piles.apply(instruction, iat);
+        }
+
+        assert_eq!(
+            initial,
+            recover_initial(&piles, &instructions, iat).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_not_enough_crates() {
+        let final_piles = parse_piles(&mut "[A]    \n 1   2 ".lines());
+        let (_, instructions) = parse_puzzle("[A]    \n 1   2 \n\nmove 1 from 1 to 2");
+
+        let err = recover_initial(
+            &final_piles,
+            &instructions,
+            InstructionApplicationType::Type_9000,
+        )
+        .unwrap_err();
+
+        assert!(matches!(
+            err,
+            InverseError::NotEnoughCrates {
+                index: 0,
+                available: 0,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,633 @@
+use std::fmt;
+
+pub mod animate;
+pub mod checked;
+pub mod drawing;
+pub mod inverse;
+pub mod optimize;
+pub mod replay;
+
+use itertools::Itertools;
+use nom::multi::separated_list1;
+// use bytes::complete as all the bytes are there, and no
+// streaming parser is needed (bytes::streaming)
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take},
+    character::complete::digit1,
+    combinator::{all_consuming, map, map_opt, map_res},
+    sequence::{delimited, preceded, tuple},
+    Finish, IResult,
+};
+use smallvec::SmallVec;
+
+// usually a single letter, but any label up to `Crate::MAX_LEN` bytes
+// works. The label is stored inline so that crates stay `Copy` and
+// moving them around doesn't allocate.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Crate {
+    len: u8,
+    label: [u8; Crate::MAX_LEN],
+}
+
+impl Crate {
+    pub const MAX_LEN: usize = 7;
+
+    // None if the label is empty or too long
+    pub fn new(label: &str) -> Option<Crate> {
+        if label.is_empty() || label.len() > Crate::MAX_LEN {
+            return None;
+        }
+
+        let mut krate = Crate {
+            len: label.len() as u8,
+            label: [0; Crate::MAX_LEN],
+        };
+        krate.label[..label.len()].copy_from_slice(label.as_bytes());
+
+        Some(krate)
+    }
+
+    pub fn label(&self) -> &str {
+        // only ever filled from a &str in `Crate::new`
+        std::str::from_utf8(&self.label[..self.len as usize]).unwrap()
+    }
+}
+
+impl From<char> for Crate {
+    fn from(c: char) -> Self {
+        Crate::new(c.encode_utf8(&mut [0; 4])).unwrap()
+    }
+}
+
+impl fmt::Debug for Crate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+impl fmt::Display for Crate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub quantity: usize,
+    pub src: usize,
+    pub dst: usize,
+}
+
+impl Instruction {
+    // moving the same crates back undoes a move, for both crane models:
+    // the CrateMover 9000 reverses their order again, the CrateMover 9001
+    // keeps it.
+    pub fn reversed(&self) -> Instruction {
+        Instruction {
+            quantity: self.quantity,
+            src: self.dst,
+            dst: self.src,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // back from indexes to pile numbers
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.quantity,
+            self.src + 1,
+            self.dst + 1
+        )
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct Piles(pub Vec<Vec<Crate>>);
+
+impl fmt::Debug for Piles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, pile) in self.0.iter().enumerate() {
+            writeln!(f, "Pile {}: {:?}", i, pile)?;
+        }
+        Ok(())
+    }
+}
+// writes the piles in the same drawing format as the puzzle input, so
+// that the output of this can be parsed again by `parse_piles`. Piles get
+// wider when there are longer labels or pile numbers.
+impl fmt::Display for Piles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_drawing(f, |_, _, krate| krate)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+#[allow(non_camel_case_types)]
+pub enum InstructionApplicationType {
+    Type_9000,
+    Type_9001,
+    Type_9001_smallVec,
+    Type_9000_drain,
+    Type_9001_drain,
+}
+impl Piles {
+    // writes the drawing, `style` gets the pile index, the level and the
+    // crate as `[A]`, and can wrap it in e.g. terminal colors
+    pub fn write_drawing(
+        &self,
+        f: &mut impl fmt::Write,
+        style: impl Fn(usize, usize, String) -> String,
+    ) -> fmt::Result {
+        let height = self.0.iter().map(Vec::len).max().unwrap_or(0);
+
+        let label_width = self
+            .0
+            .iter()
+            .flatten()
+            .map(|krate| krate.label().chars().count())
+            .max()
+            .unwrap_or(1);
+        let number_width = self.0.len().to_string().len();
+        let width = (label_width + 2).max(number_width);
+
+        // crates and pile numbers are both centered, which keeps every
+        // crate right above its pile number: ` 1 ` for the usual 3 wide piles
+        let center = |s: String, styled: String| {
+            let left = (width - s.chars().count()) / 2;
+            let right = width - left - s.chars().count();
+            format!("{:left$}{styled}{:right$}", "", "")
+        };
+
+        for level in (0..height).rev() {
+            let line = self
+                .0
+                .iter()
+                .enumerate()
+                .map(|(i, pile)| match pile.get(level) {
+                    Some(krate) => {
+                        let krate = format!("[{krate}]");
+                        center(krate.clone(), style(i, level, krate))
+                    }
+                    None => " ".repeat(width),
+                })
+                .join(" ");
+            writeln!(f, "{line}")?;
+        }
+
+        let footer = (1..=self.0.len())
+            .map(|n| center(n.to_string(), n.to_string()))
+            .join(" ");
+        writeln!(f, "{footer}")
+    }
+
+    // the crates on top of each pile, empty piles are skipped
+    pub fn tops(&self) -> String {
+        self.0.iter().filter_map(|pile| pile.last()).join("")
+    }
+
+    pub fn apply(&mut self, instruction: &Instruction, iat: InstructionApplicationType) {
+        match iat {
+            InstructionApplicationType::Type_9000 => self.apply_9000(instruction),
+            InstructionApplicationType::Type_9001 => self.apply_9001(instruction),
+            InstructionApplicationType::Type_9001_smallVec => self.apply_9001_smallvec(instruction),
+            InstructionApplicationType::Type_9000_drain => self.apply_9000_drain(instruction),
+            InstructionApplicationType::Type_9001_drain => self.apply_9001_drain(instruction),
+        }
+    }
+
+    // only valid right after `instruction` was applied with the same `iat`
+    pub fn undo(&mut self, instruction: &Instruction, iat: InstructionApplicationType) {
+        self.apply(&instruction.reversed(), iat);
+    }
+
+    pub fn apply_9000(&mut self, instruction: &Instruction) {
+        for _ in 0..instruction.quantity {
+            let e = self.0[instruction.src].pop().unwrap();
+            self.0[instruction.dst].push(e);
+        }
+    }
+
+    pub fn apply_9001(&mut self, instruction: &Instruction) {
+        // cant do the following code, as the borrow checker
+        // can't know that src and dst will never point to the same value.
+        // "crate" is a keyword, so use different spelling.
+        // for krate in (0..instruction.quantity)
+        //     .map(|_| self.0[instruction.src].pop().unwrap())
+        //     .rev()
+        // {
+        //     self.0[instruction.dst].push(krate);
+        // }
+
+        for krate in (0..instruction.quantity)
+            .map(|_| self.0[instruction.src].pop().unwrap())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+        {
+            self.0[instruction.dst].push(krate);
+        }
+    }
+
+    pub fn apply_9001_smallvec(&mut self, instruction: &Instruction) {
+        // see remarks in apply_9001()!
+
+        for krate in (0..instruction.quantity)
+            .map(|_| self.0[instruction.src].pop().unwrap())
+            .collect::<SmallVec<[_; 64]>>()
+            .into_iter()
+            .rev()
+        {
+            self.0[instruction.dst].push(krate);
+        }
+    }
+
+    // borrows both piles of a move at the same time, which the borrow
+    // checker allows when going through `split_at_mut`. Returns None when
+    // both are the same pile.
+    fn src_dst_mut(
+        &mut self,
+        instruction: &Instruction,
+    ) -> Option<(&mut Vec<Crate>, &mut Vec<Crate>)> {
+        let (src, dst) = (instruction.src, instruction.dst);
+
+        if src < dst {
+            let (left, right) = self.0.split_at_mut(dst);
+            Some((&mut left[src], &mut right[0]))
+        } else if src > dst {
+            let (left, right) = self.0.split_at_mut(src);
+            Some((&mut right[0], &mut left[dst]))
+        } else {
+            None
+        }
+    }
+
+    // same as apply_9000(), but moves all the crates at once without any
+    // temporary storage
+    pub fn apply_9000_drain(&mut self, instruction: &Instruction) {
+        // moving crates onto the pile they came from changes nothing,
+        // with either crane model
+        let Some((src, dst)) = self.src_dst_mut(instruction) else {
+            return;
+        };
+
+        let at = src.len() - instruction.quantity;
+        dst.extend(src.drain(at..).rev());
+    }
+
+    // same as apply_9001(), without the temporary Vec
+    pub fn apply_9001_drain(&mut self, instruction: &Instruction) {
+        let Some((src, dst)) = self.src_dst_mut(instruction) else {
+            return;
+        };
+
+        let at = src.len() - instruction.quantity;
+        dst.extend(src.drain(at..));
+    }
+}
+
+pub fn parse_crate(i: &str) -> IResult<&str, Crate> {
+    let first_char = |s: &str| Crate::from(s.chars().next().unwrap());
+    let f = delimited(tag("["), take(1_usize), tag("]"));
+
+    map(f, first_char)(i)
+}
+
+pub fn parse_hole(i: &str) -> IResult<&str, ()> {
+    map(tag("   "), drop)(i)
+}
+
+pub fn parse_crate_or_hole(i: &str) -> IResult<&str, Option<Crate>> {
+    alt((map(parse_crate, Some), map(parse_hole, |_| None)))(i)
+}
+
+pub fn parse_crate_line(i: &str) -> IResult<&str, Vec<Option<Crate>>> {
+    // let (mut i, c) = parse_crate_or_hole(i)?;
+    // let mut v = vec![c];
+
+    // loop {
+    //     let (next_i, maybe_c) = opt(preceded(tag(" "), parse_crate_or_hole))(i)?;
+    //     match maybe_c {
+    //         Some(c) => v.push(c),
+    //         None => break,
+    //     }
+    //     i = next_i;
+    // }
+
+    // Ok((i, v))
+
+    separated_list1(tag(" "), parse_crate_or_hole)(i)
+}
+
+pub fn parse_number(i: &str) -> IResult<&str, usize> {
+    map_res(digit1, |s: &str| s.parse::<usize>())(i)
+}
+
+// convert numbers to indexes
+pub fn parse_pile_number(i: &str) -> IResult<&str, usize> {
+    // pile 0 does not exist, so don't underflow on it
+    map_opt(parse_number, |i| i.checked_sub(1))(i)
+}
+
+pub fn parse_instruction(i: &str) -> IResult<&str, Instruction> {
+    map(
+        tuple((
+            preceded(tag("move "), parse_number),
+            preceded(tag(" from "), parse_pile_number),
+            preceded(tag(" to "), parse_pile_number),
+        )),
+        |(quantity, src, dst)| Instruction { quantity, src, dst },
+    )(i)
+}
+
+pub fn transpose_reverse<T>(v: Vec<Vec<Option<T>>>) -> Vec<Vec<T>> {
+    assert!(!v.is_empty());
+
+    let len = v[0].len();
+    let mut iters: Vec<_> = v.into_iter().map(|n| n.into_iter()).collect();
+
+    (0..len)
+        .map(|_| {
+            // trading extra memory usage now for less allocations later
+            let mut v = Vec::with_capacity(256);
+            v.extend(iters.iter_mut().rev().filter_map(|n| n.next().unwrap()));
+            v
+        })
+        .collect()
+}
+
+// parses the crate drawing up to and including the empty line after it,
+// panics on anything malformed
+pub fn parse_piles<'a>(lines: &mut impl Iterator<Item = &'a str>) -> Piles {
+    let drawing: Vec<_> = lines.take_while(|line| !line.is_empty()).collect();
+
+    drawing::parse_drawing(&drawing).unwrap_or_else(|e| panic!("bad crate drawing: {e}"))
+}
+
+// parses a whole puzzle input, panics on anything malformed.
+// see `checked::run_checked` for a version reporting errors instead.
+pub fn parse_puzzle(input: &str) -> (Piles, Vec<Instruction>) {
+    let mut lines = input.lines();
+
+    let piles = parse_piles(&mut lines);
+
+    let instructions = lines
+        .map(|line| all_consuming(parse_instruction)(line).finish().unwrap().1)
+        .collect();
+
+    (piles, instructions)
+}
+
+#[derive(Clone, Debug)]
+pub struct Stack {
+    pub name: usize,
+    pub stack: Vec<char>,
+}
+
+impl Stack {
+    pub fn new(number: usize) -> Stack {
+        Stack {
+            name: number,
+            stack: vec![],
+        }
+    }
+}
+#[derive(Clone, Debug)]
+pub struct Stacks {
+    pub stacks: Vec<Stack>,
+}
+
+impl Stacks {
+    pub fn new(initial_stack_layout_strs: Vec<&str>) -> Stacks {
+        // a stack is represented by 3 chars: either three spaces, or [,char,]
+        // two stacks are separated by a single space
+        // a line describing two stacks is 3+1+3 chars
+        // a line describing three stacks is 3+1+3+1+3=11 chars
+        let mut stacks: Vec<Stack> = Vec::new();
+
+        let mut n_stacks = 0;
+
+        // NOTE the reversed line order here: this starts the stack construction
+        // at the bottom, going up. The lines in the file are given from top to
+        // bottom though, for humans to read!
+        for line in initial_stack_layout_strs.iter().rev() {
+            let chars: Vec<char> = line.chars().collect();
+
+            if n_stacks == 0 {
+                // TODO this way is kind of ugly...
+
+                // this line contains the stack names, so grab the number of stacks
+                // from there
+                n_stacks = initial_stack_layout_strs
+                    .last()
+                    .unwrap()
+                    .split_whitespace()
+                    .count();
+
+                continue;
+            }
+
+            if stacks.len() == 0 {
+                // TODO is this the way to do it in Rust?
+                for i in 0..n_stacks {
+                    stacks.push(Stack::new(i + 1));
+                }
+            }
+
+            for i in 0..n_stacks {
+                // skip the bracket, skip the whitespace between stacks, skip the stacks
+                let char_index = 1 + i * 3 + i * 1;
+
+                // lines can be shorter when the trailing whitespace got
+                // stripped, see `drawing::parse_drawing` for a parser that
+                // handles more than this one
+                let stack_char = chars.get(char_index).copied().unwrap_or(' ');
+                // println!("stack_char {}: {}", i, stack_char);
+
+                if stack_char == ' ' {
+                    // nothing here on the stack
+                    continue;
+                }
+
+                stacks[i].stack.push(stack_char);
+            }
+        }
+
+        Stacks { stacks }
+    }
+
+    // borrows both stacks of a move at the same time, so crates can go
+    // from one to the other without a temporary stack in between.
+    // Returns None when both are the same stack.
+    fn move_stacks_mut(
+        &mut self,
+        instruction: &MoveInstruction,
+    ) -> Option<(&mut Stack, &mut Stack)> {
+        let (from, to) = (instruction.from - 1, instruction.to - 1);
+
+        if from < to {
+            let (left, right) = self.stacks.split_at_mut(to);
+            Some((&mut left[from], &mut right[0]))
+        } else if from > to {
+            let (left, right) = self.stacks.split_at_mut(from);
+            Some((&mut right[0], &mut left[to]))
+        } else {
+            None
+        }
+    }
+
+    pub fn apply_CrateMover9000(&mut self, instruction: &MoveInstruction) {
+        // moving crates onto the stack they came from changes nothing
+        let Some((from_stack, to_stack)) = self.move_stacks_mut(instruction) else {
+            return;
+        };
+
+        let at = from_stack.stack.len() - instruction.count;
+        to_stack.stack.extend(from_stack.stack.drain(at..).rev());
+    }
+
+    pub fn apply_CrateMover9001(&mut self, instruction: &MoveInstruction) {
+        let Some((from_stack, to_stack)) = self.move_stacks_mut(instruction) else {
+            return;
+        };
+
+        let at = from_stack.stack.len() - instruction.count;
+        to_stack.stack.extend(from_stack.stack.drain(at..));
+    }
+
+    pub fn crates_on_top(&self) -> String {
+        let mut result = String::new();
+
+        for stack in self.stacks.iter() {
+            let crate_on_top = stack.stack.last().unwrap().to_string();
+            result.push_str(&String::from(crate_on_top));
+        }
+
+        result
+    }
+}
+#[derive(Debug)]
+pub struct MoveInstruction {
+    pub count: usize,
+    pub from: usize,
+    pub to: usize,
+}
+
+impl MoveInstruction {
+    pub fn new_from_string(s: &str) -> Self {
+        let iter: Vec<&str> = s.split_whitespace().collect();
+
+        // TODO how to do this via the iterator?
+
+        // example:
+        // move 1 from 2 to 1
+        let count = iter[1].parse::<usize>().unwrap();
+        let from = iter[3].parse::<usize>().unwrap();
+        let to = iter[5].parse::<usize>().unwrap();
+
+        MoveInstruction { count, from, to }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parse_piles, parse_puzzle, Instruction, InstructionApplicationType, MoveInstruction, Stacks,
+    };
+    use itertools::Itertools;
+    use test_case::test_case;
+
+    const SAMPLE: &str = "    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 
+
+move 1 from 2 to 1
+move 3 from 1 to 3
+move 2 from 2 to 1
+move 1 from 1 to 2
+";
+
+    #[test_case(SAMPLE)]
+    #[test_case(include_str!("../input.txt"))]
+    fn test_render_round_trip(input: &str) {
+        let (piles, instructions) = parse_puzzle(input);
+
+        let rendered = format!("{piles}\n{}\n", instructions.iter().join("\n"));
+
+        assert_eq!(input, rendered);
+    }
+
+    #[test_case(InstructionApplicationType::Type_9000, "CMZ")]
+    #[test_case(InstructionApplicationType::Type_9001, "MCD")]
+    fn test_render_intermediate_state(iat: InstructionApplicationType, tops: &str) {
+        let (mut piles, instructions) = parse_puzzle(SAMPLE);
+
+        for instruction in &instructions {
+            piles.apply(instruction, iat);
+
+            // every intermediate state can be parsed back
+            let rendered = piles.to_string();
+            let reparsed = parse_piles(&mut rendered.lines());
+            assert_eq!(piles.0.len(), reparsed.0.len());
+            assert_eq!(rendered, reparsed.to_string());
+        }
+
+        assert_eq!(tops, piles.tops());
+    }
+
+    #[test_case(
+        InstructionApplicationType::Type_9000,
+        InstructionApplicationType::Type_9000_drain
+    )]
+    #[test_case(
+        InstructionApplicationType::Type_9001,
+        InstructionApplicationType::Type_9001_smallVec
+    )]
+    #[test_case(
+        InstructionApplicationType::Type_9001,
+        InstructionApplicationType::Type_9001_drain
+    )]
+    fn test_appliers_agree(iat: InstructionApplicationType, other_iat: InstructionApplicationType) {
+        let (mut piles, mut instructions) = parse_puzzle(include_str!("../input.txt"));
+        // moving onto the same pile is a special case for the drain versions
+        instructions.push(Instruction {
+            quantity: 2,
+            src: 3,
+            dst: 3,
+        });
+        let mut other_piles = piles.clone();
+
+        for instruction in &instructions {
+            piles.apply(instruction, iat);
+            other_piles.apply(instruction, other_iat);
+
+            assert_eq!(piles, other_piles);
+        }
+    }
+
+    #[test]
+    fn test_stacks_match_piles() {
+        let input = include_str!("../input.txt");
+        let (initial_stack_layout, instructions) = input.split_once("\n\n").unwrap();
+
+        let mut stacks_9000 = Stacks::new(initial_stack_layout.lines().collect());
+        let mut stacks_9001 = stacks_9000.clone();
+        for line in instructions.lines() {
+            let instruction = MoveInstruction::new_from_string(line);
+            stacks_9000.apply_CrateMover9000(&instruction);
+            stacks_9001.apply_CrateMover9001(&instruction);
+        }
+
+        let (mut piles_9000, instructions) = parse_puzzle(input);
+        let mut piles_9001 = piles_9000.clone();
+        for instruction in &instructions {
+            piles_9000.apply_9000(instruction);
+            piles_9001.apply_9001(instruction);
+        }
+
+        assert_eq!(piles_9000.tops(), stacks_9000.crates_on_top());
+        assert_eq!(piles_9001.tops(), stacks_9001.crates_on_top());
+    }
+}
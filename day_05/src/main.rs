@@ -1,20 +1,13 @@
-use std::fmt;
 use std::fs;
 
-use itertools::Itertools;
-use nom::bytes::complete::take_while1;
-use nom::multi::separated_list1;
-// use bytes::complete as all the bytes are there, and no
-// streaming parser is needed (bytes::streaming)
-use nom::{
-    branch::alt,
-    bytes::complete::{tag, take},
-    character::complete::digit1,
-    combinator::{all_consuming, map, map_res, opt},
-    sequence::{delimited, preceded, tuple},
-    Finish, IResult,
+use day_05::{
+    animate, checked, inverse, optimize, parse_instruction, parse_piles, parse_puzzle, replay,
+    InstructionApplicationType, MoveInstruction, Stacks,
 };
-use smallvec::SmallVec;
+use itertools::Itertools;
+use nom::{combinator::all_consuming, Finish};
+
+use miette::GraphicalReportHandler;
 
 use std::alloc::System;
 use tracking_allocator::{
@@ -60,172 +53,13 @@ impl AllocationTracker for StdoutTracker {
     }
 }
 
-#[derive(Clone, Copy)]
-struct Crate(char);
-
-impl fmt::Debug for Crate {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
-    }
-}
-impl fmt::Display for Crate {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt::Debug::fmt(self, f)
-    }
-}
-#[derive(Clone, Debug)]
-struct Instruction {
-    quantity: usize,
-    src: usize,
-    dst: usize,
-}
-
-#[derive(Clone)]
-struct Piles(Vec<Vec<Crate>>);
-
-impl fmt::Debug for Piles {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for (i, pile) in self.0.iter().enumerate() {
-            writeln!(f, "Pile {}: {:?}", i, pile)?;
-        }
-        Ok(())
-    }
-}
-#[derive(Debug)]
-enum InstructionApplicationType {
-    Type_9000,
-    Type_9001,
-    Type_9001_smallVec,
-}
-impl Piles {
-    fn apply_9000(&mut self, instruction: &Instruction) {
-        for _ in 0..instruction.quantity {
-            let e = self.0[instruction.src].pop().unwrap();
-            self.0[instruction.dst].push(e);
-        }
-    }
-
-    fn apply_9001(&mut self, instruction: &Instruction) {
-        // cant do the following code, as the borrow checker
-        // can't know that src and dst will never point to the same value.
-        // "crate" is a keyword, so use different spelling.
-        // for krate in (0..instruction.quantity)
-        //     .map(|_| self.0[instruction.src].pop().unwrap())
-        //     .rev()
-        // {
-        //     self.0[instruction.dst].push(krate);
-        // }
-
-        for krate in (0..instruction.quantity)
-            .map(|_| self.0[instruction.src].pop().unwrap())
-            .collect::<Vec<_>>()
-            .into_iter()
-            .rev()
-        {
-            self.0[instruction.dst].push(krate);
-        }
-    }
-
-    fn apply_9001_smallvec(&mut self, instruction: &Instruction) {
-        // see remarks in apply_9001()!
-
-        for krate in (0..instruction.quantity)
-            .map(|_| self.0[instruction.src].pop().unwrap())
-            .collect::<SmallVec<[_; 64]>>()
-            .into_iter()
-            .rev()
-        {
-            self.0[instruction.dst].push(krate);
-        }
-    }
-}
-
-fn parse_crate(i: &str) -> IResult<&str, Crate> {
-    let first_char = |s: &str| Crate(s.chars().next().unwrap());
-    let f = delimited(tag("["), take(1_usize), tag("]"));
-
-    map(f, first_char)(i)
-}
-
-fn parse_hole(i: &str) -> IResult<&str, ()> {
-    map(tag("   "), drop)(i)
-}
-
-fn parse_crate_or_hole(i: &str) -> IResult<&str, Option<Crate>> {
-    alt((map(parse_crate, Some), map(parse_hole, |_| None)))(i)
-}
-
-fn parse_crate_line(i: &str) -> IResult<&str, Vec<Option<Crate>>> {
-    // let (mut i, c) = parse_crate_or_hole(i)?;
-    // let mut v = vec![c];
-
-    // loop {
-    //     let (next_i, maybe_c) = opt(preceded(tag(" "), parse_crate_or_hole))(i)?;
-    //     match maybe_c {
-    //         Some(c) => v.push(c),
-    //         None => break,
-    //     }
-    //     i = next_i;
-    // }
-
-    // Ok((i, v))
-
-    separated_list1(tag(" "), parse_crate_or_hole)(i)
-}
-
-fn parse_number(i: &str) -> IResult<&str, usize> {
-    map_res(digit1, |s: &str| s.parse::<usize>())(i)
-}
-
-// convert numbers to indexes
-fn parse_pile_number(i: &str) -> IResult<&str, usize> {
-    map(parse_number, |i| i - 1)(i)
-}
-
-fn parse_instruction(i: &str) -> IResult<&str, Instruction> {
-    map(
-        tuple((
-            preceded(tag("move "), parse_number),
-            preceded(tag(" from "), parse_pile_number),
-            preceded(tag(" to "), parse_pile_number),
-        )),
-        |(quantity, src, dst)| Instruction { quantity, src, dst },
-    )(i)
-}
-
-fn transpose_reverse<T>(v: Vec<Vec<Option<T>>>) -> Vec<Vec<T>> {
-    assert!(!v.is_empty());
-
-    let len = v[0].len();
-    let mut iters: Vec<_> = v.into_iter().map(|n| n.into_iter()).collect();
-
-    (0..len)
-        .map(|_| {
-            // trading extra memory usage now for less allocations later
-            let mut v = Vec::with_capacity(256);
-            v.extend(iters.iter_mut().rev().filter_map(|n| n.next().unwrap()));
-            v
-        })
-        .collect()
-}
-
 fn functional_style() -> color_eyre::Result<()> {
     let mut lines = include_str!("../input.txt").lines();
 
-    let crate_lines: Vec<_> = (&mut lines)
-        .map_while(|line| {
-            all_consuming(parse_crate_line)(line)
-                .finish()
-                .ok()
-                .map(|(_, line)| line)
-        })
-        .collect();
-
-    // consume the empty line between the stack config
+    // also consumes the empty line between the stack config
     // and the instructions
-    assert!(lines.next().unwrap().is_empty());
+    let mut piles = parse_piles(&mut lines);
 
-    let mut piles = Piles(transpose_reverse(crate_lines));
     let mut piles_9001 = piles.clone();
     let mut piles_9001_smallvec = piles.clone();
     println!("Piles:\n{piles:?}");
@@ -256,6 +90,16 @@ fn functional_style() -> color_eyre::Result<()> {
                 piles_9001_smallvec.apply_9001_smallvec(&i);
             }
         }
+        InstructionApplicationType::Type_9000_drain => {
+            for i in instructions {
+                piles.apply_9000_drain(&i);
+            }
+        }
+        InstructionApplicationType::Type_9001_drain => {
+            for i in instructions {
+                piles_9001.apply_9001_drain(&i);
+            }
+        }
     }
     println!("Allocations with {iat:?}:");
     AllocationRegistry::disable_tracking();
@@ -310,155 +154,6 @@ fn functional_style() -> color_eyre::Result<()> {
     Ok(())
 }
 
-#[derive(Clone, Debug)]
-struct Stack {
-    name: usize,
-    stack: Vec<char>,
-}
-
-impl Stack {
-    fn new(number: usize) -> Stack {
-        Stack {
-            name: number,
-            stack: vec![],
-        }
-    }
-}
-#[derive(Clone, Debug)]
-struct Stacks {
-    stacks: Vec<Stack>,
-}
-
-impl Stacks {
-    fn new(initial_stack_layout_strs: Vec<&str>) -> Stacks {
-        // a stack is represented by 3 chars: either three spaces, or [,char,]
-        // two stacks are separated by a single space
-        // a line describing two stacks is 3+1+3 chars
-        // a line describing three stacks is 3+1+3+1+3=11 chars
-        let mut stacks: Vec<Stack> = Vec::new();
-
-        let mut n_stacks = 0;
-
-        // NOTE the reversed line order here: this starts the stack construction
-        // at the bottom, going up. The lines in the file are given from top to
-        // bottom though, for humans to read!
-        for line in initial_stack_layout_strs.iter().rev() {
-            println!("line: {}", line);
-            let chars: Vec<char> = line.chars().collect();
-
-            if n_stacks == 0 {
-                // TODO this way is kind of ugly...
-
-                // this line contains the stack names, so grab the number of stacks
-                // from there
-                n_stacks = initial_stack_layout_strs
-                    .last()
-                    .unwrap()
-                    .split_whitespace()
-                    .count();
-
-                continue;
-            }
-
-            if stacks.len() == 0 {
-                // TODO is this the way to do it in Rust?
-                for i in 0..n_stacks {
-                    stacks.push(Stack::new(i + 1));
-                }
-            }
-
-            for i in 0..n_stacks {
-                // skip the bracket, skip the whitespace between stacks, skip the stacks
-                let char_index = 1 + i * 3 + i * 1;
-
-                let stack_char = chars[char_index];
-                // println!("stack_char {}: {}", i, stack_char);
-
-                if stack_char == ' ' {
-                    // nothing here on the stack
-                    continue;
-                }
-
-                stacks[i].stack.push(stack_char);
-            }
-        }
-
-        Stacks { stacks }
-    }
-
-    pub fn apply_CrateMover9000(&mut self, instruction: &MoveInstruction) {
-        let from_stack: &mut Stack = &mut self.stacks[instruction.from - 1];
-
-        let count = instruction.count;
-
-        // TODO how to do this without copying the values to a temporary stack?
-        let mut temp_stack = Stack::new(usize::MAX);
-
-        for _ in 0..count {
-            let v = from_stack.stack.pop().unwrap();
-
-            temp_stack.stack.push(v);
-        }
-
-        let to_stack: &mut Stack = &mut self.stacks[instruction.to - 1];
-        for v in temp_stack.stack {
-            to_stack.stack.push(v);
-        }
-    }
-
-    pub fn apply_CrateMover9001(&mut self, instruction: &MoveInstruction) {
-        let from_stack: &mut Stack = &mut self.stacks[instruction.from - 1];
-
-        let count = instruction.count;
-
-        // TODO how to do this without copying the values to a temporary stack?
-        let mut temp_stack = Stack::new(usize::MAX);
-
-        for _ in 0..count {
-            let v = from_stack.stack.pop().unwrap();
-
-            temp_stack.stack.push(v);
-        }
-
-        let to_stack: &mut Stack = &mut self.stacks[instruction.to - 1];
-        for v in temp_stack.stack.iter().rev() {
-            to_stack.stack.push(*v);
-        }
-    }
-
-    pub fn crates_on_top(&self) -> String {
-        let mut result = String::new();
-
-        for stack in self.stacks.iter() {
-            let crate_on_top = stack.stack.last().unwrap().to_string();
-            result.push_str(&String::from(crate_on_top));
-        }
-
-        result
-    }
-}
-#[derive(Debug)]
-struct MoveInstruction {
-    count: usize,
-    from: usize,
-    to: usize,
-}
-
-impl MoveInstruction {
-    pub fn new_from_string(s: &str) -> Self {
-        let iter: Vec<&str> = s.split_whitespace().collect();
-
-        // TODO how to do this via the iterator?
-
-        // example:
-        // move 1 from 2 to 1
-        let count = iter[1].parse::<usize>().unwrap();
-        let from = iter[3].parse::<usize>().unwrap();
-        let to = iter[5].parse::<usize>().unwrap();
-
-        MoveInstruction { count, from, to }
-    }
-}
 fn imperative_style() -> color_eyre::Result<()> {
     let input_str = "    [D]    
 [N] [C]    
@@ -537,10 +232,201 @@ move 1 from 1 to 2";
     Ok(())
 }
 
+fn checked_style() -> color_eyre::Result<()> {
+    let input = include_str!("../input.txt");
+
+    for iat in [
+        InstructionApplicationType::Type_9000,
+        InstructionApplicationType::Type_9001,
+    ] {
+        let run = checked::run_checked(input, iat)?;
+
+        if !run.errors.is_empty() {
+            let report = checked::BadInstructions {
+                src: input.to_string(),
+                errors: run.errors,
+            };
+            let mut s = String::new();
+            GraphicalReportHandler::new()
+                .render_report(&mut s, &report)
+                .unwrap();
+            println!("{s}");
+        }
+
+        println!(
+            "{iat:?}: applied {} instructions, answer = {}",
+            run.n_applied,
+            run.piles.tops()
+        );
+    }
+
+    Ok(())
+}
+
+// applies the first `n_steps` instructions and prints the result as a new
+// puzzle input, with the instructions that are left
+fn render_style(n_steps: usize, iat: InstructionApplicationType) -> color_eyre::Result<()> {
+    let (mut piles, instructions) = parse_puzzle(include_str!("../input.txt"));
+
+    let (done, remaining) = instructions.split_at(n_steps.min(instructions.len()));
+    for instruction in done {
+        piles.apply(instruction, iat);
+    }
+
+    println!("{piles}");
+    for instruction in remaining {
+        println!("{instruction}");
+    }
+
+    Ok(())
+}
+
+fn replay_style(iat: InstructionApplicationType) -> color_eyre::Result<()> {
+    let mut replay = replay::Replay::new(include_str!("../input.txt"), iat)?;
+    let mut snapshots = vec![];
+
+    println!("commands: [n]ext (or empty line), [b]ack, [j]ump <n>, [p]rint, [s]ave, [r]estore [i], [q]uit");
+
+    for line in std::io::stdin().lines() {
+        let line = line?;
+        let mut words = line.split_whitespace();
+
+        match words.next() {
+            None | Some("n") => match replay.step_forward() {
+                Some(instruction) => println!("applied: {instruction}"),
+                None => println!("at the end of the program"),
+            },
+            Some("b") => match replay.step_back() {
+                Some(instruction) => println!("undid: {instruction}"),
+                None => println!("at the start of the program"),
+            },
+            Some("j") => match words.next().map(str::parse) {
+                Some(Ok(position)) => replay.jump_to(position),
+                _ => println!("usage: j <number of instructions to apply>"),
+            },
+            Some("p") => print!("{}", replay.piles()),
+            Some("s") => {
+                snapshots.push(replay.snapshot());
+                println!("saved snapshot {}", snapshots.len() - 1);
+            }
+            Some("r") => {
+                let index = words
+                    .next()
+                    .and_then(|i| i.parse().ok())
+                    .unwrap_or(snapshots.len().saturating_sub(1));
+                match snapshots.get(index) {
+                    Some(snapshot) => replay.restore(snapshot),
+                    None => println!("no snapshot {index}"),
+                }
+            }
+            Some("q") => break,
+            Some(command) => println!("unknown command: {command}"),
+        }
+
+        match replay.next_instruction() {
+            Some(instruction) => println!(
+                "{}/{} instructions applied, next: {instruction}",
+                replay.position(),
+                replay.len()
+            ),
+            None => println!(
+                "{}/{} instructions applied, top crates: {}",
+                replay.position(),
+                replay.len(),
+                replay.piles().tops()
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+// runs the program forward, then recovers the initial layout from the
+// final one. With a path, the drawing in that file is taken as the final
+// layout instead.
+fn inverse_style(iat: InstructionApplicationType, path: Option<&str>) -> color_eyre::Result<()> {
+    let (final_piles, instructions) = match path {
+        Some(path) => parse_puzzle(&fs::read_to_string(path)?),
+        None => {
+            let (mut piles, instructions) = parse_puzzle(include_str!("../input.txt"));
+            for instruction in &instructions {
+                piles.apply(instruction, iat);
+            }
+            (piles, instructions)
+        }
+    };
+    println!("Final layout:\n{final_piles}");
+
+    let initial_piles = inverse::recover_initial(&final_piles, &instructions, iat)?;
+    println!("Initial layout with {iat:?}:\n{initial_piles}");
+
+    Ok(())
+}
+
+// shortens the program and checks that it still gives the same answer
+fn optimize_style(iat: InstructionApplicationType) -> color_eyre::Result<()> {
+    let (piles, instructions) = parse_puzzle(include_str!("../input.txt"));
+
+    let optimized = optimize::optimize(&instructions, iat);
+    println!(
+        "{iat:?}: {} instructions optimized to {}, equivalent: {}",
+        instructions.len(),
+        optimized.len(),
+        optimize::equivalent(&piles, &instructions, &optimized, iat)
+    );
+
+    Ok(())
+}
+
+// the crane model from the command line, the CrateMover 9000 by default
+fn parse_iat(arg: Option<&String>) -> color_eyre::Result<InstructionApplicationType> {
+    match arg.map(String::as_str) {
+        None | Some("9000") => Ok(InstructionApplicationType::Type_9000),
+        Some("9001") => Ok(InstructionApplicationType::Type_9001),
+        Some(other) => Err(color_eyre::eyre::eyre!(
+            "{other:?}: not a crane, try 9000 or 9001"
+        )),
+    }
+}
+
 fn main() -> color_eyre::Result<()> {
-    imperative_style()?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        Some("check") => checked_style()?,
+        Some("render") => {
+            // e.g. `render 100 9001`
+            let n_steps = args.get(1).map_or(Ok(0), |n| n.parse())?;
+            let iat = parse_iat(args.get(2))?;
+            render_style(n_steps, iat)?
+        }
+        Some("replay") => {
+            let iat = parse_iat(args.get(1))?;
+            replay_style(iat)?
+        }
+        Some("animate") => {
+            // e.g. `animate 9001 50`, with the delay between instructions in ms
+            let iat = parse_iat(args.get(1))?;
+            let delay = args.get(2).map_or(Ok(100), |ms| ms.parse())?;
+            let mut replay = replay::Replay::new(include_str!("../input.txt"), iat)?;
+            let delay = std::time::Duration::from_millis(delay).max(animate::MIN_DELAY);
+            animate::animate(&mut replay, delay)?
+        }
+        Some("optimize") => {
+            let iat = parse_iat(args.get(1))?;
+            optimize_style(iat)?
+        }
+        Some("inverse") => {
+            // e.g. `inverse 9001 final.txt`
+            let iat = parse_iat(args.get(1))?;
+            inverse_style(iat, args.get(2).map(String::as_str))?
+        }
+        _ => {
+            imperative_style()?;
 
-    functional_style()?;
+            functional_style()?;
+        }
+    }
 
     Ok(())
 }
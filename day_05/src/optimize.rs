@@ -0,0 +1,187 @@
+use crate::{Instruction, InstructionApplicationType, Piles};
+
+fn is_9000(iat: InstructionApplicationType) -> bool {
+    matches!(
+        iat,
+        InstructionApplicationType::Type_9000 | InstructionApplicationType::Type_9000_drain
+    )
+}
+
+// What `previous` followed by `next` can be replaced with, if anything:
+// `Some(None)` when they cancel out, `Some(Some(_))` when they become one
+// move.
+//
+// Merging only works for the CrateMover 9000, which moves crates one at a
+// time anyway: `move 2 from 1 to 2` then `move 3 from 1 to 2` is the same
+// as `move 5 from 1 to 2`. The CrateMover 9001 keeps the order of the
+// crates it picks up, so the two crates of the first move end up below the
+// other three instead of on top of them. An exact move back cancels out
+// for both.
+fn combine(
+    previous: &Instruction,
+    next: &Instruction,
+    iat: InstructionApplicationType,
+) -> Option<Option<Instruction>> {
+    if next == &previous.reversed() {
+        return Some(None);
+    }
+    if !is_9000(iat) {
+        return None;
+    }
+
+    if (next.src, next.dst) == (previous.src, previous.dst) {
+        return Some(Some(Instruction {
+            quantity: previous.quantity + next.quantity,
+            ..previous.clone()
+        }));
+    }
+
+    // a partial move back takes the last crates of the previous move, and
+    // a larger one keeps going on the crates below them
+    if (next.src, next.dst) == (previous.dst, previous.src) {
+        return Some(Some(if previous.quantity > next.quantity {
+            Instruction {
+                quantity: previous.quantity - next.quantity,
+                ..previous.clone()
+            }
+        } else {
+            Instruction {
+                quantity: next.quantity - previous.quantity,
+                ..next.clone()
+            }
+        }));
+    }
+
+    None
+}
+
+// Shortens a crane program without changing where any crate ends up,
+// assuming every move in it has enough crates to take. Moves that don't
+// move anything are dropped, and every move is combined with the last one
+// kept so far, so that cancelling a move can let the ones around it
+// combine too.
+pub fn optimize(instructions: &[Instruction], iat: InstructionApplicationType) -> Vec<Instruction> {
+    let mut optimized: Vec<Instruction> = Vec::with_capacity(instructions.len());
+
+    for instruction in instructions {
+        if instruction.quantity == 0 || instruction.src == instruction.dst {
+            continue;
+        }
+
+        match optimized
+            .last()
+            .and_then(|previous| combine(previous, instruction, iat))
+        {
+            Some(combined) => {
+                optimized.pop();
+                // combining can also leave nothing to move
+                if let Some(combined) = combined.filter(|combined| combined.quantity > 0) {
+                    optimized.push(combined);
+                }
+            }
+            None => optimized.push(instruction.clone()),
+        }
+    }
+
+    optimized
+}
+
+// runs both programs on the same piles and compares the results
+pub fn equivalent(
+    piles: &Piles,
+    original: &[Instruction],
+    optimized: &[Instruction],
+    iat: InstructionApplicationType,
+) -> bool {
+    let run = |instructions: &[Instruction]| {
+        let mut piles = piles.clone();
+        for instruction in instructions {
+            piles.apply(instruction, iat);
+        }
+        piles
+    };
+
+    run(original) == run(optimized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{equivalent, optimize};
+    use crate::{parse_puzzle, Instruction, InstructionApplicationType, Piles};
+    use test_case::test_case;
+
+    const DRAWING: &str = "[A]
+[B] [E]
+[C] [F]
+[D] [G] [H]
+ 1   2   3 ";
+
+    fn program(input: &str) -> (Piles, Vec<Instruction>) {
+        parse_puzzle(&format!("{DRAWING}\n\n{input}"))
+    }
+
+    #[test_case(InstructionApplicationType::Type_9000, "move 2 from 1 to 3\nmove 2 from 3 to 1", ""; "9000 move back")]
+    #[test_case(InstructionApplicationType::Type_9001, "move 2 from 1 to 3\nmove 2 from 3 to 1", ""; "9001 move back")]
+    #[test_case(InstructionApplicationType::Type_9000, "move 1 from 1 to 3\nmove 2 from 1 to 3", "move 3 from 1 to 3"; "9000 merge")]
+    #[test_case(InstructionApplicationType::Type_9001, "move 1 from 1 to 3\nmove 2 from 1 to 3", "move 1 from 1 to 3\nmove 2 from 1 to 3"; "9001 no merge")]
+    #[test_case(InstructionApplicationType::Type_9000, "move 3 from 1 to 3\nmove 1 from 3 to 1", "move 2 from 1 to 3"; "9000 partial move back")]
+    #[test_case(InstructionApplicationType::Type_9000, "move 1 from 1 to 3\nmove 2 from 3 to 1", "move 1 from 3 to 1"; "9000 larger move back")]
+    #[test_case(InstructionApplicationType::Type_9001, "move 1 from 1 to 2\nmove 1 from 1 to 3\nmove 1 from 3 to 1\nmove 1 from 2 to 1", ""; "nested move backs")]
+    #[test_case(InstructionApplicationType::Type_9001, "move 2 from 2 to 2\nmove 0 from 1 to 2", ""; "no-ops")]
+    fn test_optimize(iat: InstructionApplicationType, input: &str, expected: &str) {
+        let (piles, instructions) = program(input);
+
+        let optimized = optimize(&instructions, iat);
+
+        assert_eq!(
+            expected,
+            optimized
+                .iter()
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+        assert!(equivalent(&piles, &instructions, &optimized, iat));
+    }
+
+    #[test]
+    fn test_merging_changes_9001_result() {
+        let (piles, instructions) = program("move 1 from 1 to 3\nmove 2 from 1 to 3");
+        let merged = optimize(&instructions, InstructionApplicationType::Type_9000);
+
+        assert!(!equivalent(
+            &piles,
+            &instructions,
+            &merged,
+            InstructionApplicationType::Type_9001
+        ));
+    }
+
+    #[test_case(InstructionApplicationType::Type_9000)]
+    #[test_case(InstructionApplicationType::Type_9000_drain)]
+    #[test_case(InstructionApplicationType::Type_9001)]
+    #[test_case(InstructionApplicationType::Type_9001_drain)]
+    fn test_optimize_input(iat: InstructionApplicationType) {
+        let (piles, instructions) = parse_puzzle(include_str!("../input.txt"));
+
+        // every move followed by moving half of it back and forth again,
+        // which cancels out for both crane models
+        let back_and_forth: Vec<_> = instructions
+            .iter()
+            .flat_map(|instruction| {
+                let back = Instruction {
+                    quantity: instruction.quantity / 2,
+                    ..instruction.reversed()
+                };
+                [instruction.clone(), back.clone(), back.reversed()]
+            })
+            .collect();
+
+        for program in [&instructions, &back_and_forth] {
+            let optimized = optimize(program, iat);
+
+            assert!(optimized.len() <= instructions.len());
+            assert!(equivalent(&piles, program, &optimized, iat));
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use crate::checked::{run_checked, CheckError};
+use crate::{parse_puzzle, Instruction, InstructionApplicationType, Piles};
+
+// a copy of the piles at some point of the replay
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub position: usize,
+    pub piles: Piles,
+}
+
+// steps through a crane program one instruction at a time. Going back
+// doesn't need any history of the piles: every move is undone by moving
+// the same crates back, see `Instruction::reversed`.
+pub struct Replay {
+    iat: InstructionApplicationType,
+    instructions: Vec<Instruction>,
+    piles: Piles,
+    // number of instructions applied so far
+    position: usize,
+}
+
+impl Replay {
+    // The whole program is checked with `run_checked` first, so stepping
+    // through it can't fail. The error is the first bad line.
+    pub fn new(input: &str, iat: InstructionApplicationType) -> Result<Self, CheckError> {
+        if let Some(e) = run_checked(input, iat)?.errors.into_iter().next() {
+            return Err(e);
+        }
+        let (piles, instructions) = parse_puzzle(input);
+
+        Ok(Replay {
+            iat,
+            instructions,
+            piles,
+            position: 0,
+        })
+    }
+
+    pub fn piles(&self) -> &Piles {
+        &self.piles
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    // the instruction that `step_forward` would apply next
+    pub fn next_instruction(&self) -> Option<&Instruction> {
+        self.instructions.get(self.position)
+    }
+
+    // the instruction that was applied last, i.e. the one `step_back`
+    // would undo
+    pub fn last_instruction(&self) -> Option<&Instruction> {
+        self.instructions.get(self.position.checked_sub(1)?)
+    }
+
+    // returns the applied instruction, or None at the end of the program
+    pub fn step_forward(&mut self) -> Option<&Instruction> {
+        let instruction = self.instructions.get(self.position)?;
+        self.piles.apply(instruction, self.iat);
+        self.position += 1;
+
+        Some(instruction)
+    }
+
+    // returns the undone instruction, or None at the start of the program
+    pub fn step_back(&mut self) -> Option<&Instruction> {
+        let position = self.position.checked_sub(1)?;
+        let instruction = &self.instructions[position];
+        self.piles.undo(instruction, self.iat);
+        self.position = position;
+
+        Some(instruction)
+    }
+
+    // moves forwards or backwards until `position` instructions are
+    // applied, stopping at the end of the program
+    pub fn jump_to(&mut self, position: usize) {
+        let position = position.min(self.len());
+
+        while self.position < position {
+            self.step_forward();
+        }
+        while self.position > position {
+            self.step_back();
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            position: self.position,
+            piles: self.piles.clone(),
+        }
+    }
+
+    // only valid for snapshots taken from this replay
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.position = snapshot.position;
+        self.piles = snapshot.piles.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Replay;
+    use crate::checked::CheckError;
+    use crate::{parse_puzzle, InstructionApplicationType};
+    use test_case::test_case;
+
+    #[test_case(InstructionApplicationType::Type_9000)]
+    #[test_case(InstructionApplicationType::Type_9001)]
+    #[test_case(InstructionApplicationType::Type_9001_smallVec)]
+    fn test_step_back_undoes_step_forward(iat: InstructionApplicationType) {
+        let mut replay = Replay::new(include_str!("../input.txt"), iat).unwrap();
+        let initial = replay.piles().to_string();
+
+        let mut states = vec![replay.piles().to_string()];
+        while replay.step_forward().is_some() {
+            states.push(replay.piles().to_string());
+        }
+        assert_eq!(replay.len(), replay.position());
+
+        while replay.step_back().is_some() {
+            assert_eq!(states[replay.position()], replay.piles().to_string());
+        }
+        assert_eq!(0, replay.position());
+        assert_eq!(initial, replay.piles().to_string());
+    }
+
+    #[test_case(InstructionApplicationType::Type_9000)]
+    #[test_case(InstructionApplicationType::Type_9001)]
+    fn test_jump_to(iat: InstructionApplicationType) {
+        let input = include_str!("../input.txt");
+        let (mut piles, instructions) = parse_puzzle(input);
+        let mut replay = Replay::new(input, iat).unwrap();
+
+        for instruction in &instructions[..100] {
+            piles.apply(instruction, iat);
+        }
+
+        replay.jump_to(250);
+        let snapshot = replay.snapshot();
+        replay.jump_to(100);
+        assert_eq!(piles.to_string(), replay.piles().to_string());
+
+        replay.restore(&snapshot);
+        assert_eq!(250, replay.position());
+        replay.jump_to(100);
+        assert_eq!(piles.to_string(), replay.piles().to_string());
+
+        replay.jump_to(usize::MAX);
+        assert_eq!(instructions.len(), replay.position());
+    }
+
+    #[test_case(InstructionApplicationType::Type_9000)]
+    #[test_case(InstructionApplicationType::Type_9001)]
+    fn test_invalid_program(iat: InstructionApplicationType) {
+        // pile 1 only holds one crate after the first move
+        let input = "[A]    \n[B] [C]\n 1   2 \n\nmove 1 from 1 to 2\nmove 2 from 1 to 2";
+
+        let result = Replay::new(input, iat);
+
+        assert!(matches!(
+            result,
+            Err(CheckError::NotEnoughCrates {
+                line: 6,
+                quantity: 2,
+                available: 1,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,64 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use day_06::{find_marker, find_marker_by_distinct_chars, find_marker_sliding};
+
+// xorshift, keeps the generated input the same from run to run
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+// random printable ASCII, which practically never has `window_size`
+// distinct characters in a row for large windows, followed by a marker
+fn generate(len: usize, window_size: usize) -> String {
+    let mut rng = Rng(0x2022_0006);
+    let printable = b'!'..=b'~';
+    let n_printable = printable.clone().count() as u64;
+
+    (0..len)
+        .map(|_| (b'!' + (rng.next() % n_printable) as u8) as char)
+        .chain(printable.take(window_size).map(char::from))
+        .collect()
+}
+
+fn bench_markers(c: &mut Criterion) {
+    let input = include_str!("../input.txt");
+    let generated = generate(10_000, 50);
+
+    let mut group = c.benchmark_group("markers");
+    group.sample_size(20);
+
+    for (name, input, window_size) in [
+        ("input", input, 4),
+        ("input", input, 14),
+        ("generated", generated.as_str(), 50),
+    ] {
+        let parameter = format!("{name}, window {window_size}");
+
+        group.bench_with_input(
+            BenchmarkId::new("find_marker_by_distinct_chars", &parameter),
+            &input,
+            |b, input| b.iter(|| find_marker_by_distinct_chars(input, window_size)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("find_marker", &parameter),
+            &input,
+            |b, input| b.iter(|| find_marker(input, window_size)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("find_marker_sliding", &parameter),
+            &input,
+            |b, input| b.iter(|| find_marker_sliding(input, window_size)),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_markers);
+criterion_main!(benches);
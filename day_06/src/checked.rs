@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::DistinctRun;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum MarkerError {
+    #[error("a marker needs a window of at least one")]
+    EmptyWindow,
+
+    #[error("the input is {len} long, which is shorter than the window of {window_size}")]
+    TooShort { len: usize, window_size: usize },
+
+    #[error("no {window_size} distinct in a row in the input of length {len}")]
+    NotFound { len: usize, window_size: usize },
+}
+
+fn not_found(len: usize, window_size: usize) -> MarkerError {
+    if len < window_size {
+        MarkerError::TooShort { len, window_size }
+    } else {
+        MarkerError::NotFound { len, window_size }
+    }
+}
+
+// Looks at the input byte by byte, so a character of several bytes in
+// UTF-8 counts as several. The position is in bytes, and can be used to
+// slice `input`.
+pub fn find_byte_marker(input: &[u8], window_size: usize) -> Result<usize, MarkerError> {
+    if window_size == 0 {
+        return Err(MarkerError::EmptyWindow);
+    }
+
+    let mut run = DistinctRun::new();
+    input
+        .iter()
+        .position(|&byte| run.push(byte) >= window_size as u64)
+        .map(|pos| pos + 1)
+        .ok_or_else(|| not_found(input.len(), window_size))
+}
+
+// Looks at the input char by char, which can be anything in UTF-8. The
+// position is in chars, like `find_marker_by_distinct_chars` returns it.
+pub fn find_char_marker(input: &str, window_size: usize) -> Result<usize, MarkerError> {
+    find_marker_in(input.chars(), window_size)
+}
+
+// the same as `DistinctRun`, for any alphabet
+pub fn find_marker_in<T: Eq + Hash>(
+    items: impl IntoIterator<Item = T>,
+    window_size: usize,
+) -> Result<usize, MarkerError> {
+    if window_size == 0 {
+        return Err(MarkerError::EmptyWindow);
+    }
+
+    // positions are stored + 1, like in `DistinctRun`
+    let mut last_seen: HashMap<T, usize> = HashMap::new();
+    let mut run_start = 0;
+    let mut len = 0;
+
+    for item in items {
+        len += 1;
+        if let Some(previous) = last_seen.insert(item, len) {
+            run_start = run_start.max(previous);
+        }
+
+        if len - run_start >= window_size {
+            return Ok(len);
+        }
+    }
+
+    Err(not_found(len, window_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_byte_marker, find_char_marker, find_marker_in, MarkerError};
+    use test_case::test_case;
+
+    #[test_case(7, "mjqjpqmgbljsphdztnvjfqwrcgsmlb", 4)]
+    #[test_case(5, "bvwbjplbgvbhsrlpgdmjqwftvncz", 4)]
+    #[test_case(6, "nppdvjthqldpwncqszvftbrmjlhg", 4)]
+    #[test_case(10, "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 4)]
+    #[test_case(11, "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 4)]
+    #[test_case(19, "mjqjpqmgbljsphdztnvjfqwrcgsmlb", 14)]
+    #[test_case(23, "bvwbjplbgvbhsrlpgdmjqwftvncz", 14)]
+    #[test_case(23, "nppdvjthqldpwncqszvftbrmjlhg", 14)]
+    #[test_case(29, "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 14)]
+    #[test_case(26, "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 14)]
+    fn test_find_marker(index: usize, input: &str, window_size: usize) {
+        assert_eq!(Ok(index), find_byte_marker(input.as_bytes(), window_size));
+        assert_eq!(Ok(index), find_char_marker(input, window_size));
+
+        // the same with every letter as a greek one, two bytes each in UTF-8
+        let greek: String = input
+            .chars()
+            .map(|c| char::from_u32('α' as u32 + (c as u32 - 'a' as u32)).unwrap())
+            .collect();
+        assert_eq!(Ok(index), find_char_marker(&greek, window_size));
+    }
+
+    #[test_case(4, "🦀🎄🦀⭐🎁", 3)]
+    #[test_case(3, "日本語", 3)]
+    fn test_find_char_marker_multibyte(index: usize, input: &str, window_size: usize) {
+        assert_eq!(Ok(index), find_char_marker(input, window_size));
+    }
+
+    #[test]
+    fn test_find_byte_marker_multibyte() {
+        // `é` is 0xc3 0xa9, so two distinct bytes
+        assert_eq!(Ok(2), find_byte_marker("éé".as_bytes(), 2));
+        assert_eq!(
+            Err(MarkerError::NotFound {
+                len: 2,
+                window_size: 2
+            }),
+            find_char_marker("éé", 2)
+        );
+    }
+
+    #[test]
+    fn test_any_alphabet() {
+        assert_eq!(Ok(4), find_marker_in([1u32, 1, 1_000_000, 7], 3));
+    }
+
+    #[test_case("", 4, MarkerError::TooShort { len: 0, window_size: 4 }; "empty")]
+    #[test_case("abc", 4, MarkerError::TooShort { len: 3, window_size: 4 }; "short")]
+    #[test_case("日本日", 4, MarkerError::TooShort { len: 3, window_size: 4 }; "short in chars")]
+    #[test_case("abcabcabc", 4, MarkerError::NotFound { len: 9, window_size: 4 }; "no marker")]
+    #[test_case("abcd", 0, MarkerError::EmptyWindow; "empty window")]
+    fn test_find_char_marker_errors(input: &str, window_size: usize, err: MarkerError) {
+        assert_eq!(Err(err), find_char_marker(input, window_size));
+    }
+
+    #[test]
+    fn test_find_byte_marker_counts_bytes() {
+        // 3 chars, but 9 bytes
+        assert_eq!(
+            Err(MarkerError::NotFound {
+                len: 9,
+                window_size: 4
+            }),
+            find_byte_marker("日日日".as_bytes(), 4)
+        );
+    }
+}
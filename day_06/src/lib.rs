@@ -0,0 +1,244 @@
+use itertools::Itertools;
+
+pub mod checked;
+pub mod stream;
+
+// returns true if at least one character is found multiple times in v
+fn contains_duplicate_chars(v: &[char]) -> bool {
+    let l = v.len();
+
+    for i in 0..l {
+        for j in 0..l {
+            if i == j {
+                // same character, dont look at this one
+                continue;
+            }
+
+            if v[i] == v[j] {
+                return true;
+            }
+        }
+    }
+
+    return false;
+}
+pub fn find_marker_by_distinct_chars(s: &str, n_distinct_chars: usize) -> usize {
+    if s.len() < n_distinct_chars {
+        panic!(
+            "Passed in string should have at least {} characters, but got:\n{}",
+            n_distinct_chars, s
+        );
+    }
+
+    let chars: Vec<char> = s.chars().collect();
+
+    let mut current_slice_begin = 0;
+
+    let max_begin_index = s.len() - n_distinct_chars;
+
+    // println!("string length: {}", s.len());
+    // println!("max begin index: {max_begin_index}");
+
+    while current_slice_begin <= max_begin_index {
+        let v = &chars[current_slice_begin..current_slice_begin + n_distinct_chars];
+
+        if !contains_duplicate_chars(v) {
+            // current_slice_begin now contains the *beginning* of the marker,
+            // *not* the start of the data packet. The marker is n characters.
+            return current_slice_begin + n_distinct_chars;
+        }
+
+        current_slice_begin += 1;
+    }
+
+    panic!("No start of packet marker was found in this:\n{}", s);
+}
+pub fn find_start_of_packet_marker(s: &str) -> usize {
+    find_marker_by_distinct_chars(s, 4)
+}
+
+pub fn find_start_of_message_marker(s: &str) -> usize {
+    find_marker_by_distinct_chars(s, 14)
+}
+
+pub fn find_marker(input: &str, window_size: usize) -> Option<usize> {
+    input
+        .as_bytes()
+        .windows(window_size)
+        .position(|window| window.iter().unique().count() == window_size)
+        .map(|pos| pos + window_size)
+}
+
+// Tracks the run of distinct bytes that ends at the last byte pushed. The
+// table holds where every byte was last seen, so the run starts right after
+// the last repeated byte, without looking at the bytes in it again.
+#[derive(Clone)]
+pub struct DistinctRun {
+    // positions are stored + 1, 0 is for bytes that weren't seen yet
+    last_seen: [u64; 256],
+    run_start: u64,
+    // number of bytes pushed so far
+    position: u64,
+}
+
+impl DistinctRun {
+    pub fn new() -> Self {
+        DistinctRun {
+            last_seen: [0; 256],
+            run_start: 0,
+            position: 0,
+        }
+    }
+
+    // returns the length of the run ending at `byte`
+    pub fn push(&mut self, byte: u8) -> u64 {
+        self.run_start = self.run_start.max(self.last_seen[byte as usize]);
+        self.position += 1;
+        self.last_seen[byte as usize] = self.position;
+
+        self.position - self.run_start
+    }
+
+    // the next run starts with the next byte pushed
+    pub fn restart(&mut self) {
+        self.run_start = self.position;
+    }
+}
+
+impl Default for DistinctRun {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// same as `find_marker`, in a single pass with no work per window. An empty
+// window is never a marker.
+pub fn find_marker_sliding(input: &str, window_size: usize) -> Option<usize> {
+    if window_size == 0 {
+        return None;
+    }
+    let mut run = DistinctRun::new();
+
+    input
+        .as_bytes()
+        .iter()
+        .position(|&byte| run.push(byte) >= window_size as u64)
+        .map(|pos| pos + 1)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct MarkerScan {
+    // for every window size, all positions right after a window of that
+    // many distinct bytes, like `find_marker` returns for the first one
+    pub positions: Vec<Vec<usize>>,
+    pub longest_run: usize,
+}
+
+// Finds the markers for all window sizes at once: a window of k distinct
+// bytes ends at every byte where the run of distinct bytes is at least k
+// long. There are no markers for a window size of 0.
+pub fn scan_markers(input: &str, window_sizes: &[usize]) -> MarkerScan {
+    let mut run = DistinctRun::new();
+    let mut scan = MarkerScan {
+        positions: vec![vec![]; window_sizes.len()],
+        longest_run: 0,
+    };
+
+    for (i, &byte) in input.as_bytes().iter().enumerate() {
+        let run_length = run.push(byte) as usize;
+        scan.longest_run = scan.longest_run.max(run_length);
+
+        for (positions, &window_size) in scan.positions.iter_mut().zip(window_sizes) {
+            if window_size > 0 && run_length >= window_size {
+                positions.push(i + 1);
+            }
+        }
+    }
+
+    scan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_marker, find_marker_sliding, scan_markers};
+    use itertools::Itertools;
+    use test_case::test_case;
+
+    #[test_case(7, "mjqjpqmgbljsphdztnvjfqwrcgsmlb", 4)]
+    #[test_case(5, "bvwbjplbgvbhsrlpgdmjqwftvncz", 4)]
+    #[test_case(6, "nppdvjthqldpwncqszvftbrmjlhg", 4)]
+    #[test_case(10, "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 4)]
+    #[test_case(11, "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 4)]
+    fn test_find_marker(index: usize, input: &str, window_size: usize) {
+        assert_eq!(Some(index), find_marker(input, window_size));
+    }
+
+    #[test_case(7, "mjqjpqmgbljsphdztnvjfqwrcgsmlb", 4)]
+    #[test_case(5, "bvwbjplbgvbhsrlpgdmjqwftvncz", 4)]
+    #[test_case(6, "nppdvjthqldpwncqszvftbrmjlhg", 4)]
+    #[test_case(10, "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 4)]
+    #[test_case(11, "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 4)]
+    #[test_case(19, "mjqjpqmgbljsphdztnvjfqwrcgsmlb", 14)]
+    #[test_case(23, "bvwbjplbgvbhsrlpgdmjqwftvncz", 14)]
+    #[test_case(23, "nppdvjthqldpwncqszvftbrmjlhg", 14)]
+    #[test_case(29, "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 14)]
+    #[test_case(26, "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 14)]
+    fn test_find_marker_sliding(index: usize, input: &str, window_size: usize) {
+        assert_eq!(Some(index), find_marker_sliding(input, window_size));
+    }
+
+    #[test]
+    fn test_find_marker_sliding_matches_find_marker() {
+        let input = include_str!("../input.txt");
+
+        for window_size in 1..=27 {
+            assert_eq!(
+                find_marker(input, window_size),
+                find_marker_sliding(input, window_size),
+                "window size {window_size}"
+            );
+        }
+    }
+
+    #[test_case("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 18)]
+    #[test_case("bvwbjplbgvbhsrlpgdmjqwftvncz", 18)]
+    #[test_case("nppdvjthqldpwncqszvftbrmjlhg", 18)]
+    #[test_case("abcabc", 3)]
+    #[test_case("", 0)]
+    fn test_longest_run(input: &str, longest_run: usize) {
+        assert_eq!(longest_run, scan_markers(input, &[]).longest_run);
+    }
+
+    #[test_case(""; "empty input")]
+    #[test_case("mjqjpqmgbljsphdztnvjfqwrcgsmlb")]
+    fn test_empty_window(input: &str) {
+        assert_eq!(None, find_marker_sliding(input, 0));
+    }
+
+    #[test]
+    fn test_scan_markers_empty_window() {
+        let scan = scan_markers("abc", &[0, 2]);
+
+        assert_eq!(vec![vec![], vec![2, 3]], scan.positions);
+    }
+
+    #[test]
+    fn test_scan_markers() {
+        let input = include_str!("../input.txt");
+        let window_sizes = [1, 4, 14, 20];
+
+        let scan = scan_markers(input, &window_sizes);
+
+        for (positions, window_size) in scan.positions.iter().zip(window_sizes) {
+            let expected: Vec<_> = input
+                .as_bytes()
+                .windows(window_size)
+                .positions(|window| window.iter().unique().count() == window_size)
+                .map(|pos| pos + window_size)
+                .collect();
+
+            assert_eq!(&expected, positions, "window size {window_size}");
+            assert_eq!(find_marker(input, window_size), positions.first().copied());
+        }
+    }
+}
@@ -1,65 +1,10 @@
-use itertools::Itertools;
+use day_06::stream::{Event, Framer};
+use day_06::{
+    checked, find_marker, find_start_of_message_marker, find_start_of_packet_marker, scan_markers,
+};
 use std::collections::HashSet;
 use std::fs;
 
-// returns true if at least one character is found multiple times in v
-fn contains_duplicate_chars(v: &[char]) -> bool {
-    let l = v.len();
-
-    for i in 0..l {
-        for j in 0..l {
-            if i == j {
-                // same character, dont look at this one
-                continue;
-            }
-
-            if v[i] == v[j] {
-                return true;
-            }
-        }
-    }
-
-    return false;
-}
-fn find_marker_by_distinct_chars(s: &str, n_distinct_chars: usize) -> usize {
-    if s.len() < n_distinct_chars {
-        panic!(
-            "Passed in string should have at least {} characters, but got:\n{}",
-            n_distinct_chars, s
-        );
-    }
-
-    let chars: Vec<char> = s.chars().collect();
-
-    let mut current_slice_begin = 0;
-
-    let max_begin_index = s.len() - n_distinct_chars;
-
-    // println!("string length: {}", s.len());
-    // println!("max begin index: {max_begin_index}");
-
-    while current_slice_begin <= max_begin_index {
-        let v = &chars[current_slice_begin..current_slice_begin + n_distinct_chars];
-
-        if !contains_duplicate_chars(v) {
-            // current_slice_begin now contains the *beginning* of the marker,
-            // *not* the start of the data packet. The marker is n characters.
-            return current_slice_begin + n_distinct_chars;
-        }
-
-        current_slice_begin += 1;
-    }
-
-    panic!("No start of packet marker was found in this:\n{}", s);
-}
-fn find_start_of_packet_marker(s: &str) -> usize {
-    find_marker_by_distinct_chars(s, 4)
-}
-
-fn find_start_of_message_marker(s: &str) -> usize {
-    find_marker_by_distinct_chars(s, 14)
-}
-
 fn imperative_style() -> color_eyre::Result<()> {
     let lines_start_of_packets = vec![
         "bvwbjplbgvbhsrlpgdmjqwftvncz",
@@ -94,17 +39,41 @@ fn imperative_style() -> color_eyre::Result<()> {
     Ok(())
 }
 
-fn find_marker(input: &str, window_size: usize) -> Option<usize> {
-    input
-        .as_bytes()
-        .windows(window_size)
-        .position(|window| window.iter().unique().count() == window_size)
-        .map(|pos| pos + window_size)
-}
-
 fn functional_style() -> color_eyre::Result<()> {
     let marker = find_marker(include_str!("../input.txt"), 4).unwrap();
     println!("Marker: {marker}");
+
+    let message = checked::find_byte_marker(include_str!("../input.txt").as_bytes(), 14)?;
+    println!("Message marker: {message}");
+
+    let window_sizes = [4, 14];
+    let scan = scan_markers(include_str!("../input.txt"), &window_sizes);
+    for (window_size, positions) in window_sizes.iter().zip(&scan.positions) {
+        println!(
+            "{} markers with window size {window_size}, the first at {:?}",
+            positions.len(),
+            positions.first()
+        );
+    }
+    println!("Longest run of distinct bytes: {}", scan.longest_run);
+    Ok(())
+}
+
+// reads the input as a stream instead of all at once
+fn stream_style() -> color_eyre::Result<()> {
+    let mut payload_len = 0;
+
+    for event in Framer::new(fs::File::open("input.txt")?) {
+        match event? {
+            Event::Marker { offset, kind } => {
+                println!("{kind:?} at {offset}, after {payload_len} bytes of payload");
+                payload_len = 0;
+            }
+            Event::Payload { bytes, .. } => payload_len += bytes.len(),
+        }
+    }
+    println!("{payload_len} bytes of payload until the end");
+
     Ok(())
 }
 
@@ -113,20 +82,7 @@ fn main() -> color_eyre::Result<()> {
 
     functional_style()?;
 
-    Ok(())
-}
+    stream_style()?;
 
-#[cfg(test)]
-mod tests {
-    use crate::find_marker;
-    use test_case::test_case;
-
-    #[test_case(7, "mjqjpqmgbljsphdztnvjfqwrcgsmlb", 4)]
-    #[test_case(5, "bvwbjplbgvbhsrlpgdmjqwftvncz", 4)]
-    #[test_case(6, "nppdvjthqldpwncqszvftbrmjlhg", 4)]
-    #[test_case(10, "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 4)]
-    #[test_case(11, "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 4)]
-    fn test_find_marker(index: usize, input: &str, window_size: usize) {
-        assert_eq!(Some(index), find_marker(input, window_size));
-    }
+    Ok(())
 }
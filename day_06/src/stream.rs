@@ -0,0 +1,304 @@
+use std::collections::VecDeque;
+use std::io::{self, Read};
+
+use crate::DistinctRun;
+
+// how much is read at a time, and the most payload bytes in one event
+const CHUNK_SIZE: usize = 8 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarkerKind {
+    StartOfPacket,
+    StartOfMessage,
+}
+
+impl MarkerKind {
+    pub fn window_size(self) -> usize {
+        match self {
+            MarkerKind::StartOfPacket => 4,
+            MarkerKind::StartOfMessage => 14,
+        }
+    }
+
+    // a packet starts with its start-of-packet marker, and its message
+    // with the start-of-message marker after that
+    fn next(self) -> MarkerKind {
+        match self {
+            MarkerKind::StartOfPacket => MarkerKind::StartOfMessage,
+            MarkerKind::StartOfMessage => MarkerKind::StartOfPacket,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    // `offset` is right after the marker, like what `find_marker` returns.
+    // A start-of-message marker can start inside the start-of-packet marker
+    // before it, the bytes they share are only in the first one.
+    Marker { offset: u64, kind: MarkerKind },
+    // bytes that are not part of a marker, starting at `offset`. The bytes
+    // between two markers can come in several events, none of them longer
+    // than `CHUNK_SIZE`.
+    Payload { offset: u64, bytes: Vec<u8> },
+}
+
+// Splits a stream into markers and the payload between them, looking for a
+// start-of-packet marker first and then alternating between both kinds.
+// Only a chunk of the input and the bytes that could still turn out to be
+// the next marker are kept in memory, so the stream can be of any size.
+pub struct Framer<R> {
+    reader: R,
+    chunk: Vec<u8>,
+    kind: MarkerKind,
+    run: DistinctRun,
+    // the last bytes, which are the next marker if it ends at the next byte
+    window: VecDeque<u8>,
+    payload: Vec<u8>,
+    payload_offset: u64,
+    // number of bytes read so far
+    offset: u64,
+    events: VecDeque<Event>,
+    done: bool,
+}
+
+impl<R: Read> Framer<R> {
+    pub fn new(reader: R) -> Self {
+        Framer {
+            reader,
+            chunk: vec![0; CHUNK_SIZE],
+            kind: MarkerKind::StartOfPacket,
+            run: DistinctRun::new(),
+            window: VecDeque::with_capacity(MarkerKind::StartOfMessage.window_size()),
+            payload: vec![],
+            payload_offset: 0,
+            offset: 0,
+            events: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    fn flush_payload(&mut self) {
+        if !self.payload.is_empty() {
+            self.events.push_back(Event::Payload {
+                offset: self.payload_offset,
+                bytes: std::mem::take(&mut self.payload),
+            });
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        let run_length = self.run.push(byte);
+        self.offset += 1;
+
+        self.window.push_back(byte);
+        if self.window.len() > self.kind.window_size() {
+            // too far back to be part of the marker
+            if self.payload.is_empty() {
+                self.payload_offset = self.offset - self.window.len() as u64;
+            }
+            self.payload.extend(self.window.pop_front());
+            if self.payload.len() == CHUNK_SIZE {
+                self.flush_payload();
+            }
+        }
+
+        if run_length >= self.kind.window_size() as u64 {
+            self.flush_payload();
+            self.events.push_back(Event::Marker {
+                offset: self.offset,
+                kind: self.kind,
+            });
+
+            // the marker's bytes are not payload, but the start-of-message
+            // marker can still end with some of them, so the run goes on.
+            // The next packet's markers start after the message marker.
+            self.window.clear();
+            if self.kind == MarkerKind::StartOfMessage {
+                self.run.restart();
+            }
+            self.kind = self.kind.next();
+        }
+    }
+
+    // reads the next chunk, or flushes what is left at the end of the stream
+    fn read_chunk(&mut self) -> io::Result<()> {
+        let n = loop {
+            match self.reader.read(&mut self.chunk) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                result => break result?,
+            }
+        };
+
+        if n == 0 {
+            if self.payload.is_empty() {
+                self.payload_offset = self.offset - self.window.len() as u64;
+            }
+            self.payload.extend(self.window.drain(..));
+            self.flush_payload();
+            self.done = true;
+        }
+
+        for i in 0..n {
+            self.push(self.chunk[i]);
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for Framer<R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.events.is_empty() && !self.done {
+            if let Err(e) = self.read_chunk() {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+
+        self.events.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, Framer, MarkerKind, CHUNK_SIZE};
+    use crate::find_marker;
+    use itertools::Itertools;
+    use std::io::{self, Read};
+    use test_case::test_case;
+
+    // hands out the input one byte at a time, so that markers are split
+    // over several reads
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let (mut first, rest) = self.0.split_at(self.0.len().min(1));
+            self.0 = rest;
+            first.read(buf)
+        }
+    }
+
+    fn events(input: &str) -> Vec<Event> {
+        Framer::new(Trickle(input.as_bytes()))
+            .collect::<io::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_sample() {
+        assert_eq!(
+            vec![
+                Event::Payload {
+                    offset: 0,
+                    bytes: b"mjq".to_vec()
+                },
+                Event::Marker {
+                    offset: 7,
+                    kind: MarkerKind::StartOfPacket
+                },
+                // `jpqm` is also the start of the message marker
+                Event::Marker {
+                    offset: 19,
+                    kind: MarkerKind::StartOfMessage
+                },
+                Event::Marker {
+                    offset: 23,
+                    kind: MarkerKind::StartOfPacket
+                },
+                Event::Payload {
+                    offset: 23,
+                    bytes: b"rcgsmlb".to_vec()
+                },
+            ],
+            events("mjqjpqmgbljsphdztnvjfqwrcgsmlb")
+        );
+    }
+
+    // the message marker starts 2 bytes before the packet marker ends
+    #[test_case("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 7, 19; "overlapping markers")]
+    #[test_case("bvwbjplbgvbhsrlpgdmjqwftvncz", 5, 23)]
+    #[test_case("nppdvjthqldpwncqszvftbrmjlhg", 6, 23)]
+    #[test_case("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 10, 29)]
+    #[test_case("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 11, 26)]
+    fn test_first_markers(input: &str, start_of_packet: u64, start_of_message: u64) {
+        let markers: Vec<_> = events(input)
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Marker { offset, kind } => Some((offset, kind)),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(
+            [
+                (start_of_packet, MarkerKind::StartOfPacket),
+                (start_of_message, MarkerKind::StartOfMessage)
+            ],
+            markers[..2]
+        );
+    }
+
+    #[test]
+    fn test_input() {
+        let input = include_str!("../input.txt");
+        let events = events(input);
+
+        let markers: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Marker { offset, kind } => Some((*offset as usize, *kind)),
+                _ => None,
+            })
+            .collect();
+
+        let start_of_packet = find_marker(input, 4).unwrap();
+        let start_of_message = find_marker(input, 14).unwrap();
+        assert_eq!((start_of_packet, MarkerKind::StartOfPacket), markers[0]);
+        assert_eq!((start_of_message, MarkerKind::StartOfMessage), markers[1]);
+
+        // every byte is either payload or part of a marker
+        let mut offset = 0;
+        for event in &events {
+            match event {
+                Event::Marker { offset: end, kind } => {
+                    // a marker can start before the end of the previous one
+                    let start = *end as usize - kind.window_size();
+                    assert!(start <= offset);
+                    assert!(input[start..*end as usize].bytes().all_unique());
+                    offset = *end as usize;
+                }
+                Event::Payload {
+                    offset: start,
+                    bytes,
+                } => {
+                    assert_eq!(offset, *start as usize);
+                    assert_eq!(&input.as_bytes()[offset..offset + bytes.len()], bytes);
+                    offset += bytes.len();
+                }
+            }
+        }
+        assert_eq!(input.len(), offset);
+    }
+
+    #[test]
+    fn test_long_payload_is_chunked() {
+        let input = "ab".repeat(CHUNK_SIZE) + "abcd";
+        let events = events(&input);
+
+        assert_eq!(3, events.len());
+        assert!(events[..2].iter().all(|event| matches!(
+            event,
+            Event::Payload { bytes, .. } if bytes.len() == CHUNK_SIZE
+        )));
+        assert_eq!(
+            Event::Marker {
+                offset: input.len() as u64,
+                kind: MarkerKind::StartOfPacket
+            },
+            events[2]
+        );
+    }
+}
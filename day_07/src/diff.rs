@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use camino::Utf8PathBuf;
+use id_tree::NodeId;
+use serde::Serialize;
+
+use crate::filesystem::{Filesystem, Usage};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    // `size` is the total size for directories
+    Added { size: u64 },
+    Removed { size: u64 },
+    // only files are resized, directories are in `FsDiff::dirs`
+    Resized { old: u64, new: u64 },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct EntryChange {
+    pub path: Utf8PathBuf,
+    pub is_dir: bool,
+    #[serde(flatten)]
+    pub change: Change,
+}
+
+// a directory whose total size changed, one that is only on one side is 0
+// on the other
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DirDelta {
+    pub path: Utf8PathBuf,
+    pub old: u64,
+    pub new: u64,
+    pub delta: i64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct FsDiff {
+    pub changes: Vec<EntryChange>,
+    pub dirs: Vec<DirDelta>,
+}
+
+impl FsDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.dirs.is_empty()
+    }
+}
+
+// one side of the diff
+struct Side<'a> {
+    fs: &'a Filesystem,
+    usage: HashMap<NodeId, Usage>,
+}
+
+impl Side<'_> {
+    fn size(&self, id: Option<&NodeId>) -> u64 {
+        id.map_or(0, |id| self.usage[id].size)
+    }
+
+    fn is_dir(&self, id: Option<&NodeId>) -> bool {
+        matches!(id, Some(id) if self.fs.entry(id).is_dir)
+    }
+}
+
+struct Differ<'a> {
+    old: Side<'a>,
+    new: Side<'a>,
+    diff: FsDiff,
+}
+
+impl Differ<'_> {
+    // `path` is `old` and `new`, either of which can be missing
+    fn walk(&mut self, path: Utf8PathBuf, old: Option<&NodeId>, new: Option<&NodeId>) {
+        let (old_is_dir, new_is_dir) = (self.old.is_dir(old), self.new.is_dir(new));
+        let (old_size, new_size) = (self.old.size(old), self.new.size(new));
+
+        // an entry that changed from a file to a directory or the other way
+        // around is removed and added back
+        let kind_changed = old.is_some() && new.is_some() && old_is_dir != new_is_dir;
+        if old.is_some() && (new.is_none() || kind_changed) {
+            self.diff.changes.push(EntryChange {
+                path: path.clone(),
+                is_dir: old_is_dir,
+                change: Change::Removed { size: old_size },
+            });
+        }
+        if new.is_some() && (old.is_none() || kind_changed) {
+            self.diff.changes.push(EntryChange {
+                path: path.clone(),
+                is_dir: new_is_dir,
+                change: Change::Added { size: new_size },
+            });
+        }
+        if old.is_some() && new.is_some() && !old_is_dir && !new_is_dir && old_size != new_size {
+            self.diff.changes.push(EntryChange {
+                path: path.clone(),
+                is_dir: false,
+                change: Change::Resized {
+                    old: old_size,
+                    new: new_size,
+                },
+            });
+        }
+
+        let old_dir_size = if old_is_dir { old_size } else { 0 };
+        let new_dir_size = if new_is_dir { new_size } else { 0 };
+        if old_dir_size != new_dir_size {
+            self.diff.dirs.push(DirDelta {
+                path: path.clone(),
+                old: old_dir_size,
+                new: new_dir_size,
+                delta: new_dir_size as i64 - old_dir_size as i64,
+            });
+        }
+
+        // what's under an added or removed directory isn't listed on its
+        // own, it's part of the directory's size
+        if !(old_is_dir && new_is_dir) {
+            return;
+        }
+        let (old, new) = (old.unwrap(), new.unwrap());
+
+        let old_children: Vec<_> = self
+            .old
+            .fs
+            .children(old)
+            .map(|(name, id)| (name.to_string(), id.clone()))
+            .collect();
+        for (name, old_child) in &old_children {
+            let new_child = self.new.fs.child(new, name).cloned();
+            self.walk(path.join(name), Some(old_child), new_child.as_ref());
+        }
+        let new_children: Vec<_> = self
+            .new
+            .fs
+            .children(new)
+            .filter(|(name, _)| self.old.fs.child(old, name).is_none())
+            .map(|(name, id)| (name.to_string(), id.clone()))
+            .collect();
+        for (name, new_child) in &new_children {
+            self.walk(path.join(name), None, Some(new_child));
+        }
+    }
+}
+
+// Compares two filesystems entry by entry, by path. The changes are in
+// the order of `old`, with what's only in `new` after the rest of a
+// directory.
+pub fn diff(old: &Filesystem, new: &Filesystem) -> FsDiff {
+    let mut differ = Differ {
+        old: Side {
+            fs: old,
+            usage: old.usage(),
+        },
+        new: Side {
+            fs: new,
+            usage: new.usage(),
+        },
+        diff: FsDiff::default(),
+    };
+    differ.walk("/".into(), Some(old.root()), Some(new.root()));
+    differ.diff
+}
+
+// like `diff`, `+` for added, `-` for removed and `~` for resized entries,
+// directories end with a `/`
+impl fmt::Display for FsDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for EntryChange {
+            path,
+            is_dir,
+            change,
+        } in &self.changes
+        {
+            let slash = if *is_dir { "/" } else { "" };
+            match change {
+                Change::Added { size } => writeln!(f, "+ {path}{slash} ({size})")?,
+                Change::Removed { size } => writeln!(f, "- {path}{slash} ({size})")?,
+                Change::Resized { old, new } => writeln!(
+                    f,
+                    "~ {path} ({old} -> {new}, {:+})",
+                    *new as i64 - *old as i64
+                )?,
+            }
+        }
+
+        if !self.dirs.is_empty() {
+            writeln!(f, "directories:")?;
+        }
+        for dir in &self.dirs {
+            writeln!(
+                f,
+                "{:>+12} {} ({} -> {})",
+                dir.delta, dir.path, dir.old, dir.new
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, Change, DirDelta, EntryChange};
+    use crate::interpret::{interpret, Strictness};
+
+    const SMALL: &str = include_str!("../input_small.txt");
+
+    fn diff_transcripts(old: &str, new: &str) -> super::FsDiff {
+        diff(
+            &interpret(old, Strictness::Stop).unwrap().fs,
+            &interpret(new, Strictness::Stop).unwrap().fs,
+        )
+    }
+
+    fn change(path: &str, is_dir: bool, change: Change) -> EntryChange {
+        EntryChange {
+            path: path.into(),
+            is_dir,
+            change,
+        }
+    }
+
+    fn delta(path: &str, old: u64, new: u64) -> DirDelta {
+        DirDelta {
+            path: path.into(),
+            old,
+            new,
+            delta: new as i64 - old as i64,
+        }
+    }
+
+    #[test]
+    fn test_same() {
+        let input = include_str!("../input.txt");
+        assert!(diff_transcripts(input, input).is_empty());
+    }
+
+    #[test]
+    fn test_cleanup() {
+        // `/a/e` is gone, `b.txt` shrank and `/d/l` and `/x` are new
+        let new = SMALL
+            .replace("dir e\n", "")
+            .replace("$ cd e\n$ ls\n584 i\n$ cd ..\n", "")
+            .replace("14848514 b.txt", "1000 b.txt")
+            + "\n100 l\n$ cd ..\n$ ls\ndir x";
+
+        let diff = diff_transcripts(SMALL, &new);
+
+        assert_eq!(
+            vec![
+                change("/a/e", true, Change::Removed { size: 584 }),
+                change(
+                    "/b.txt",
+                    false,
+                    Change::Resized {
+                        old: 14848514,
+                        new: 1000
+                    }
+                ),
+                change("/d/l", false, Change::Added { size: 100 }),
+                change("/x", true, Change::Added { size: 0 }),
+            ],
+            diff.changes
+        );
+        assert_eq!(
+            vec![
+                delta("/", 48381165, 48381165 - 584 - 14847514 + 100),
+                delta("/a", 94853, 94853 - 584),
+                delta("/a/e", 584, 0),
+                delta("/d", 24933642, 24933742),
+            ],
+            diff.dirs
+        );
+    }
+
+    #[test]
+    fn test_file_becomes_dir() {
+        let new = SMALL.replace("8504156 c.dat", "dir c.dat") + "\n$ cd /c.dat\n$ ls\n10 z";
+
+        let diff = diff_transcripts(SMALL, &new);
+
+        assert_eq!(
+            vec![
+                change("/c.dat", false, Change::Removed { size: 8504156 }),
+                change("/c.dat", true, Change::Added { size: 10 }),
+            ],
+            diff.changes
+        );
+        assert_eq!(
+            vec![
+                delta("/", 48381165, 48381165 - 8504156 + 10),
+                delta("/c.dat", 0, 10),
+            ],
+            diff.dirs
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let new = SMALL.replace("584 i", "600 i");
+
+        let json = serde_json::to_value(diff_transcripts(SMALL, &new)).unwrap();
+
+        assert_eq!(
+            serde_json::json!({
+                "changes": [
+                    {"path": "/a/e/i", "is_dir": false, "change": "resized", "old": 584, "new": 600},
+                ],
+                "dirs": [
+                    {"path": "/", "old": 48381165, "new": 48381181, "delta": 16},
+                    {"path": "/a", "old": 94853, "new": 94869, "delta": 16},
+                    {"path": "/a/e", "old": 584, "new": 600, "delta": 16},
+                ],
+            }),
+            json
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let new = SMALL
+            .replace("584 i", "600 i")
+            .replace("dir d\n", "dir d\n1 n\n");
+
+        assert_eq!(
+            "~ /a/e/i (584 -> 600, +16)
++ /n (1)
+directories:
+         +17 / (48381165 -> 48381182)
+         +16 /a (94853 -> 94869)
+         +16 /a/e (584 -> 600)
+",
+            diff_transcripts(SMALL, &new).to_string()
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use camino::Utf8PathBuf;
+use id_tree::NodeId;
+
+use crate::filesystem::{Filesystem, Usage};
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DuOptions {
+    // only directories this deep are listed, `/` is at depth 0
+    pub max_depth: Option<usize>,
+    pub human_readable: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DuEntry {
+    pub path: Utf8PathBuf,
+    pub depth: usize,
+    pub usage: Usage,
+}
+
+// Formats a size like `du -h` does, with powers of 1024: `584`, `14.2M`.
+// One decimal is shown below 10 of a unit, none above.
+pub fn human_size(size: u64) -> String {
+    const UNITS: [&str; 6] = ["K", "M", "G", "T", "P", "E"];
+
+    if size < 1024 {
+        return size.to_string();
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    // the next unit as soon as the value would be shown as 1024, so that
+    // `1048575` is `1.0M` and not `1024K`
+    while value.round() >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    // `9.96` has no decimal either, it's shown as `10`
+    if (value * 10.0).round() < 100.0 {
+        format!("{value:.1}{}", UNITS[unit])
+    } else {
+        format!("{value:.0}{}", UNITS[unit])
+    }
+}
+
+// the directories with their usage, largest first
+pub fn du(fs: &Filesystem, usage: &HashMap<NodeId, Usage>, options: DuOptions) -> Vec<DuEntry> {
+    let mut entries = vec![];
+
+    // the depth is tracked along the way, the tree only knows the parents
+    let mut stack = vec![(fs.root().clone(), 0)];
+    while let Some((id, depth)) = stack.pop() {
+        entries.push(DuEntry {
+            path: fs.path(&id),
+            depth,
+            usage: usage[&id],
+        });
+
+        let too_deep = matches!(options.max_depth, Some(max_depth) if depth >= max_depth);
+        if !too_deep {
+            stack.extend(
+                fs.children(&id)
+                    .filter(|(_, child)| fs.entry(child).is_dir)
+                    .map(|(_, child)| (child.clone(), depth + 1)),
+            );
+        }
+    }
+
+    entries.sort_by(|a, b| {
+        b.usage
+            .size
+            .cmp(&a.usage.size)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    entries
+}
+
+// a `du` report, one directory per line
+pub struct DuReport {
+    pub entries: Vec<DuEntry>,
+    pub options: DuOptions,
+}
+
+impl fmt::Display for DuReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            let size = match self.options.human_readable {
+                true => human_size(entry.usage.size),
+                false => entry.usage.size.to_string(),
+            };
+            writeln!(
+                f,
+                "{size:>10} {:>6} files  {}",
+                entry.usage.files, entry.path
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{du, human_size, DuOptions};
+    use crate::interpret::{interpret, Strictness};
+    use test_case::test_case;
+
+    #[test_case(0, "0")]
+    #[test_case(584, "584")]
+    #[test_case(1024, "1.0K")]
+    #[test_case(29116, "28K")]
+    #[test_case(14848514, "14M")]
+    #[test_case(48381165, "46M")]
+    #[test_case(5 * 1024 * 1024 * 1024 + 1, "5.0G")]
+    #[test_case(1023, "1023")]
+    #[test_case(1048575, "1.0M"; "just below a mebibyte")]
+    #[test_case(1024 * 1024 - 600, "1023K"; "rounded down below a mebibyte")]
+    #[test_case(10199, "10K"; "rounded up to ten")]
+    #[test_case(u64::MAX, "16E")]
+    fn test_human_size(size: u64, expected: &str) {
+        assert_eq!(expected, human_size(size));
+    }
+
+    #[test_case(None, &[("/", 48381165, 10), ("/d", 24933642, 4), ("/a", 94853, 4), ("/a/e", 584, 1)])]
+    #[test_case(Some(1), &[("/", 48381165, 10), ("/d", 24933642, 4), ("/a", 94853, 4)])]
+    #[test_case(Some(0), &[("/", 48381165, 10)])]
+    fn test_du(max_depth: Option<usize>, expected: &[(&str, u64, usize)]) {
+        let fs = interpret(include_str!("../input_small.txt"), Strictness::Stop)
+            .unwrap()
+            .fs;
+
+        let entries = du(
+            &fs,
+            &fs.usage(),
+            DuOptions {
+                max_depth,
+                human_readable: false,
+            },
+        );
+
+        let entries: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.path.as_str(), entry.usage.size, entry.usage.files))
+            .collect();
+        assert_eq!(expected, entries);
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use id_tree::{InsertBehavior, Node, NodeId, Tree};
+use indexmap::IndexMap;
+
+use crate::FsEntry;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum PathError {
+    #[error("{0}: no such file or directory")]
+    NotFound(Utf8PathBuf),
+
+    #[error("{0}: not a directory")]
+    NotADirectory(Utf8PathBuf),
+
+    #[error("there is nothing above /")]
+    AboveRoot,
+}
+
+// Whether `name` can be the name of an entry: a single normal component,
+// not `.`, `..`, empty or with a `/` in it. Anything else would end up
+// somewhere else than in its directory.
+pub fn is_entry_name(name: &str) -> bool {
+    let mut components = Utf8Path::new(name).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Utf8Component::Normal(normal)), None) if normal == name
+    )
+}
+
+// what is under an entry, a file counts itself
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub size: u64,
+    pub files: usize,
+}
+
+// The tree of entries, with the children of every directory also kept by
+// name, in the order they were added. Looking a name up doesn't need to go
+// through all the children, and there's never two entries with the same
+// name in a directory.
+pub struct Filesystem {
+    pub tree: Tree<FsEntry>,
+    root: NodeId,
+    names: HashMap<NodeId, IndexMap<String, NodeId>>,
+}
+
+impl Filesystem {
+    // an empty filesystem with only `/`
+    pub fn new() -> Self {
+        let mut tree = Tree::new();
+        let root = tree
+            .insert(
+                Node::new(FsEntry {
+                    path: "/".into(),
+                    size: 0,
+                    is_dir: true,
+                }),
+                InsertBehavior::AsRoot,
+            )
+            .expect("the tree is empty");
+
+        Filesystem {
+            tree,
+            names: HashMap::from([(root.clone(), IndexMap::new())]),
+            root,
+        }
+    }
+
+    pub fn root(&self) -> &NodeId {
+        &self.root
+    }
+
+    // node ids only ever come from this filesystem, and nothing is removed
+    pub fn node(&self, id: &NodeId) -> &Node<FsEntry> {
+        self.tree.get(id).expect("node ids are from this tree")
+    }
+
+    pub fn entry(&self, id: &NodeId) -> &FsEntry {
+        self.node(id).data()
+    }
+
+    pub fn entry_mut(&mut self, id: &NodeId) -> &mut FsEntry {
+        self.tree
+            .get_mut(id)
+            .expect("node ids are from this tree")
+            .data_mut()
+    }
+
+    pub fn parent(&self, id: &NodeId) -> Option<&NodeId> {
+        self.node(id).parent()
+    }
+
+    pub fn child(&self, dir: &NodeId, name: &str) -> Option<&NodeId> {
+        self.names.get(dir)?.get(name)
+    }
+
+    // the entries in a directory by name, in the order they were added
+    pub fn children(&self, dir: &NodeId) -> impl Iterator<Item = (&str, &NodeId)> {
+        self.names
+            .get(dir)
+            .into_iter()
+            .flatten()
+            .map(|(name, id)| (name.as_str(), id))
+    }
+
+    // Adds an entry to a directory. There must not be an entry with the
+    // same name already, see `child`.
+    pub fn insert(&mut self, dir: &NodeId, entry: FsEntry) -> NodeId {
+        let name = entry.path.to_string();
+        let is_dir = entry.is_dir;
+
+        let id = self
+            .tree
+            .insert(Node::new(entry), InsertBehavior::UnderNode(dir))
+            .expect("node ids are from this tree");
+
+        let names = self.names.get_mut(dir).expect("entries are added to dirs");
+        let previous = names.insert(name, id.clone());
+        assert!(previous.is_none(), "names in a directory are unique");
+
+        if is_dir {
+            self.names.insert(id.clone(), IndexMap::new());
+        }
+
+        id
+    }
+
+    // the full path of an entry, e.g. `/a/e/i`
+    pub fn path(&self, id: &NodeId) -> Utf8PathBuf {
+        let mut names = vec![self.entry(id).path.as_path()];
+        names.extend(
+            self.tree
+                .ancestors(id)
+                .expect("node ids are from this tree")
+                .map(|node| node.data().path.as_path()),
+        );
+
+        names.into_iter().rev().collect()
+    }
+
+    // Finds what `path` points to when in `from`, which is only used for
+    // relative paths. `..` in `/` is an error, unlike in a shell.
+    pub fn resolve(&self, from: &NodeId, path: &Utf8Path) -> Result<NodeId, PathError> {
+        let mut current = from.clone();
+
+        for component in path.components() {
+            if !self.entry(&current).is_dir {
+                return Err(PathError::NotADirectory(self.path(&current)));
+            }
+
+            current = match component {
+                Utf8Component::RootDir | Utf8Component::Prefix(_) => self.root.clone(),
+                Utf8Component::CurDir => current,
+                Utf8Component::ParentDir => {
+                    self.parent(&current).ok_or(PathError::AboveRoot)?.clone()
+                }
+                Utf8Component::Normal(name) => self
+                    .child(&current, name)
+                    .ok_or_else(|| PathError::NotFound(self.path(&current).join(name)))?
+                    .clone(),
+            };
+        }
+
+        Ok(current)
+    }
+
+    // The usage of every entry, in a single post-order pass: children come
+    // before their parent, so their usage is ready to be added up. Unlike
+    // calling `total_size` for every directory, nothing is visited twice.
+    pub fn usage(&self) -> HashMap<NodeId, Usage> {
+        let mut usage: HashMap<NodeId, Usage> = HashMap::new();
+
+        for id in self
+            .tree
+            .traverse_post_order_ids(&self.root)
+            .expect("the root is in the tree")
+        {
+            let node = self.node(&id);
+            let mut total = match node.data().is_dir {
+                true => Usage::default(),
+                false => Usage {
+                    size: node.data().size,
+                    files: 1,
+                },
+            };
+            for child in node.children() {
+                let child = usage[child];
+                total.size += child.size;
+                total.files += child.files;
+            }
+
+            usage.insert(id, total);
+        }
+
+        usage
+    }
+}
+
+impl Default for Filesystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_entry_name, Filesystem, PathError, Usage};
+    use crate::interpret::{interpret, Strictness};
+    use crate::{total_size, FsEntry};
+    use test_case::test_case;
+
+    fn dir(name: &str) -> FsEntry {
+        FsEntry {
+            path: name.into(),
+            size: 0,
+            is_dir: true,
+        }
+    }
+
+    #[test_case("a", true)]
+    #[test_case("My Documents", true)]
+    #[test_case(".a", true; "hidden")]
+    #[test_case("", false; "empty")]
+    #[test_case(".", false; "current")]
+    #[test_case("..", false; "parent")]
+    #[test_case("a/b", false; "nested")]
+    #[test_case("a/", false; "trailing slash")]
+    #[test_case("/", false; "root")]
+    fn test_is_entry_name(name: &str, expected: bool) {
+        assert_eq!(expected, is_entry_name(name));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut fs = Filesystem::new();
+        let root = fs.root().clone();
+        let a = fs.insert(&root, dir("a"));
+        let e = fs.insert(&a, dir("e"));
+        let i = fs.insert(
+            &e,
+            FsEntry {
+                path: "i".into(),
+                size: 584,
+                is_dir: false,
+            },
+        );
+
+        assert_eq!("/a/e/i", fs.path(&i));
+        assert_eq!(Ok(i.clone()), fs.resolve(&root, "a/e/i".into()));
+        assert_eq!(Ok(a.clone()), fs.resolve(&e, "..".into()));
+        assert_eq!(Ok(e.clone()), fs.resolve(&e, "/a/./e".into()));
+        assert_eq!(Err(PathError::AboveRoot), fs.resolve(&a, "../..".into()));
+        assert_eq!(
+            Err(PathError::NotFound("/a/x".into())),
+            fs.resolve(&root, "/a/x/y".into())
+        );
+        assert_eq!(
+            Err(PathError::NotADirectory("/a/e/i".into())),
+            fs.resolve(&root, "/a/e/i/..".into())
+        );
+    }
+
+    #[test]
+    fn test_usage_matches_total_size() {
+        let fs = interpret(include_str!("../input.txt"), Strictness::Stop)
+            .unwrap()
+            .fs;
+        let usage = fs.usage();
+
+        for node_id in fs.tree.traverse_pre_order_ids(fs.root()).unwrap() {
+            let size = total_size(&fs.tree, fs.node(&node_id)).unwrap();
+            assert_eq!(size, usage[&node_id].size, "{}", fs.path(&node_id));
+        }
+        assert_eq!(
+            Usage {
+                size: 46876531,
+                files: fs
+                    .tree
+                    .traverse_pre_order(fs.root())
+                    .unwrap()
+                    .filter(|node| !node.data().is_dir)
+                    .count()
+            },
+            usage[fs.root()]
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Write;
+use std::io;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use id_tree::{NodeId, Tree};
+
+use crate::filesystem::{is_entry_name, Filesystem};
+use crate::FsEntry;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Order {
+    // like the puzzle input: into every directory right after listing its
+    // parent, and back out with `cd ..`
+    DepthFirst,
+    // every directory of a level before the next level, with absolute paths
+    BreadthFirst,
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum GenerateError {
+    #[error("the tree is empty")]
+    Empty,
+
+    #[error("/ has to be a directory")]
+    RootNotADirectory,
+
+    // names with line breaks, `/`, leading whitespace or that are `.` or
+    // `..` would read back as something else
+    #[error("{0:?}: this name can't be written in a transcript")]
+    BadName(Utf8PathBuf),
+
+    #[error("{0}: there's more than one entry with this name")]
+    DuplicateName(Utf8PathBuf),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReadError {
+    #[error("{path}: {source}")]
+    Io {
+        path: Utf8PathBuf,
+        source: io::Error,
+    },
+
+    #[error("{0}: not a directory")]
+    NotADirectory(Utf8PathBuf),
+}
+
+fn bad_name(name: &str) -> bool {
+    !is_entry_name(name) || name.contains(['\n', '\r']) || name.starts_with(char::is_whitespace)
+}
+
+fn path(tree: &Tree<FsEntry>, id: &NodeId) -> Utf8PathBuf {
+    let mut names = vec![tree.get(id).unwrap().data().path.as_path()];
+    names.extend(
+        tree.ancestors(id)
+            .unwrap()
+            .map(|node| node.data().path.as_path()),
+    );
+    names.into_iter().rev().collect()
+}
+
+// the `ls` output for a directory
+fn write_ls(tree: &Tree<FsEntry>, dir: &NodeId, out: &mut String) -> Result<(), GenerateError> {
+    writeln!(out, "$ ls").unwrap();
+
+    let mut names = HashSet::new();
+    for child in tree.children_ids(dir).unwrap() {
+        let entry = tree.get(child).unwrap().data();
+        let name = entry.path.as_str();
+        if bad_name(name) {
+            return Err(GenerateError::BadName(path(tree, child)));
+        }
+        if !names.insert(name) {
+            return Err(GenerateError::DuplicateName(path(tree, child)));
+        }
+
+        match entry.is_dir {
+            true => writeln!(out, "dir {name}").unwrap(),
+            false => writeln!(out, "{} {name}", entry.size).unwrap(),
+        }
+    }
+
+    Ok(())
+}
+
+fn subdirs<'a>(tree: &'a Tree<FsEntry>, dir: &NodeId) -> impl Iterator<Item = &'a NodeId> {
+    tree.children_ids(dir)
+        .unwrap()
+        .filter(|&child| tree.get(child).unwrap().data().is_dir)
+}
+
+// Writes a transcript of `cd` and `ls` commands that lists every directory
+// once. `interpret` rebuilds the same tree from it, with the entries of
+// every directory in the same order. The name of the root isn't used, it's
+// always `/`.
+pub fn transcript(tree: &Tree<FsEntry>, order: Order) -> Result<String, GenerateError> {
+    fn depth_first(
+        tree: &Tree<FsEntry>,
+        dir: &NodeId,
+        out: &mut String,
+    ) -> Result<(), GenerateError> {
+        write_ls(tree, dir, out)?;
+        for child in subdirs(tree, dir) {
+            writeln!(out, "$ cd {}", tree.get(child).unwrap().data().path).unwrap();
+            depth_first(tree, child, out)?;
+            writeln!(out, "$ cd ..").unwrap();
+        }
+        Ok(())
+    }
+
+    let root = tree.root_node_id().ok_or(GenerateError::Empty)?;
+    if !tree.get(root).unwrap().data().is_dir {
+        return Err(GenerateError::RootNotADirectory);
+    }
+
+    let mut out = String::new();
+    writeln!(out, "$ cd /").unwrap();
+    match order {
+        Order::DepthFirst => depth_first(tree, root, &mut out)?,
+        Order::BreadthFirst => {
+            write_ls(tree, root, &mut out)?;
+
+            // `(dir, path)`, the paths are built up along the way
+            let mut queue: VecDeque<_> = subdirs(tree, root)
+                .map(|child| {
+                    let name = &tree.get(child).unwrap().data().path;
+                    (child, Utf8Path::new("/").join(name))
+                })
+                .collect();
+            while let Some((dir, dir_path)) = queue.pop_front() {
+                writeln!(out, "$ cd {dir_path}").unwrap();
+                write_ls(tree, dir, &mut out)?;
+                queue.extend(subdirs(tree, dir).map(|child| {
+                    let name = &tree.get(child).unwrap().data().path;
+                    (child, dir_path.join(name))
+                }));
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+// Reads a directory on disk, which becomes `/`. Files get their size from
+// their metadata, and the entries of a directory are sorted by name. Symlinks
+// aren't followed and, like anything else that's not a file or directory,
+// are left out.
+pub fn read_dir(root: &Utf8Path) -> Result<Filesystem, ReadError> {
+    fn walk(fs: &mut Filesystem, dir: &NodeId, path: &Utf8Path) -> Result<(), ReadError> {
+        let io_error = |path: &Utf8Path| {
+            let path = path.to_owned();
+            move |source| ReadError::Io { path, source }
+        };
+
+        let mut entries = path
+            .read_dir_utf8()
+            .map_err(io_error(path))?
+            .collect::<io::Result<Vec<_>>>()
+            .map_err(io_error(path))?;
+        entries.sort_by(|a, b| a.file_name().cmp(b.file_name()));
+
+        for entry in entries {
+            let file_type = entry.file_type().map_err(io_error(entry.path()))?;
+            if file_type.is_dir() {
+                let id = fs.insert(
+                    dir,
+                    FsEntry {
+                        path: entry.file_name().into(),
+                        size: 0,
+                        is_dir: true,
+                    },
+                );
+                walk(fs, &id, entry.path())?;
+            } else if file_type.is_file() {
+                let metadata = entry.metadata().map_err(io_error(entry.path()))?;
+                fs.insert(
+                    dir,
+                    FsEntry {
+                        path: entry.file_name().into(),
+                        size: metadata.len(),
+                        is_dir: false,
+                    },
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    if !root.is_dir() {
+        return Err(ReadError::NotADirectory(root.to_owned()));
+    }
+
+    let mut fs = Filesystem::new();
+    let root_id = fs.root().clone();
+    walk(&mut fs, &root_id, root)?;
+    Ok(fs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_dir, transcript, GenerateError, Order};
+    use crate::filesystem::Filesystem;
+    use crate::interpret::{interpret, Strictness};
+    use crate::FsEntry;
+    use test_case::test_case;
+
+    // every entry with its size, in pre-order
+    fn listing(fs: &Filesystem) -> Vec<(String, u64, bool)> {
+        let usage = fs.usage();
+        fs.tree
+            .traverse_pre_order_ids(fs.root())
+            .unwrap()
+            .map(|id| {
+                let entry = fs.entry(&id);
+                (fs.path(&id).to_string(), usage[&id].size, entry.is_dir)
+            })
+            .collect()
+    }
+
+    fn round_trip(fs: &Filesystem, order: Order) -> Filesystem {
+        let transcript = transcript(&fs.tree, order).unwrap();
+        let interpretation = interpret(&transcript, Strictness::Stop).unwrap();
+        assert!(interpretation.warnings.is_empty());
+        interpretation.fs
+    }
+
+    #[test]
+    fn test_small_depth_first() {
+        let input = include_str!("../input_small.txt");
+        let fs = interpret(input, Strictness::Stop).unwrap().fs;
+
+        // the puzzle's own transcript, plus going back up at the end
+        let expected = input.trim_end().to_string() + "\n$ cd ..\n";
+        assert_eq!(expected, transcript(&fs.tree, Order::DepthFirst).unwrap());
+    }
+
+    #[test]
+    fn test_small_breadth_first() {
+        let fs = interpret(include_str!("../input_small.txt"), Strictness::Stop)
+            .unwrap()
+            .fs;
+
+        let transcript = transcript(&fs.tree, Order::BreadthFirst).unwrap();
+        let cds: Vec<_> = transcript
+            .lines()
+            .filter(|line| line.starts_with("$ cd"))
+            .collect();
+        assert_eq!(vec!["$ cd /", "$ cd /a", "$ cd /d", "$ cd /a/e"], cds);
+    }
+
+    #[test_case(include_str!("../input_small.txt"), Order::DepthFirst)]
+    #[test_case(include_str!("../input_small.txt"), Order::BreadthFirst)]
+    #[test_case(include_str!("../input.txt"), Order::DepthFirst; "input depth first")]
+    #[test_case(include_str!("../input.txt"), Order::BreadthFirst; "input breadth first")]
+    fn test_round_trip(input: &str, order: Order) {
+        let fs = interpret(input, Strictness::Stop).unwrap().fs;
+
+        assert_eq!(listing(&fs), listing(&round_trip(&fs, order)));
+    }
+
+    #[test_case("a\nb")]
+    #[test_case(" a")]
+    #[test_case("..")]
+    #[test_case("a/b")]
+    fn test_bad_name(name: &str) {
+        let mut fs = Filesystem::new();
+        let root = fs.root().clone();
+        fs.insert(
+            &root,
+            FsEntry {
+                path: name.into(),
+                size: 1,
+                is_dir: false,
+            },
+        );
+
+        assert_eq!(
+            Err(GenerateError::BadName(format!("/{name}").into())),
+            transcript(&fs.tree, Order::DepthFirst)
+        );
+    }
+
+    #[test]
+    fn test_read_dir() {
+        let dir = std::env::temp_dir().join(format!("day_07-read-dir-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("a/with space")).unwrap();
+        std::fs::create_dir_all(dir.join("empty")).unwrap();
+        for (path, size) in [("b.txt", 14848514), ("a/f", 29116), ("a/with space/i", 584)] {
+            std::fs::File::create(dir.join(path))
+                .unwrap()
+                .set_len(size)
+                .unwrap();
+        }
+
+        let read = read_dir(dir.as_path().try_into().unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+        let fs = read.unwrap();
+
+        let expected = vec![
+            ("/".to_string(), 14848514 + 29116 + 584, true),
+            ("/a".to_string(), 29116 + 584, true),
+            ("/a/f".to_string(), 29116, false),
+            ("/a/with space".to_string(), 584, true),
+            ("/a/with space/i".to_string(), 584, false),
+            ("/b.txt".to_string(), 14848514, false),
+            ("/empty".to_string(), 0, true),
+        ];
+        assert_eq!(expected, listing(&fs));
+        for order in [Order::DepthFirst, Order::BreadthFirst] {
+            assert_eq!(expected, listing(&round_trip(&fs, order)));
+        }
+    }
+}
@@ -0,0 +1,563 @@
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use id_tree::NodeId;
+use miette::SourceSpan;
+use nom::{
+    character::complete::{line_ending, not_line_ending},
+    combinator::{all_consuming, opt},
+    sequence::terminated,
+    Finish, IResult,
+};
+use nom_supreme::error::{BaseErrorKind, ErrorTree, GenericErrorTree};
+
+use crate::filesystem::{is_entry_name, Filesystem, PathError};
+use crate::{parse_input_line, Command, Entry, FsEntry, InputLine, Span};
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq, miette::Diagnostic)]
+#[error("{line}:{column}: {kind}")]
+pub struct InterpretError {
+    // 1-based, the column counts characters
+    pub line: u32,
+    pub column: usize,
+
+    #[label("{kind}")]
+    pub bad_part: SourceSpan,
+
+    pub kind: InterpretErrorKind,
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum InterpretErrorKind {
+    #[error("unknown command `{0}`")]
+    UnknownCommand(String),
+
+    // what the parser expected, e.g. `expected "ls"`
+    #[error("{0}")]
+    Parse(String),
+
+    #[error("entry listed without `$ ls` before it")]
+    EntryWithoutLs,
+
+    #[error("{0:?}: not a name, entries can't be `.`, `..` or have a `/` in them")]
+    BadName(Utf8PathBuf),
+
+    #[error(transparent)]
+    Path(#[from] PathError),
+}
+
+// all the errors in a transcript, rendered against the transcript
+#[derive(thiserror::Error, Debug, miette::Diagnostic)]
+#[error("bad transcript")]
+pub struct BadTranscript {
+    #[source_code]
+    pub src: String,
+
+    #[related]
+    pub errors: Vec<InterpretError>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strictness {
+    // the first bad line stops the interpreter
+    Stop,
+    // bad lines are skipped, a `cd` that fails leaves the current directory
+    // as it was, like in a shell
+    Continue,
+}
+
+// things that don't stop the interpreter, but make the transcript doubtful
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub line: usize,
+    pub kind: WarningKind,
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum WarningKind {
+    #[error("{path} was listed with {old} bytes before and now with {new}, keeping {new}")]
+    ConflictingSize {
+        path: Utf8PathBuf,
+        old: u64,
+        new: u64,
+    },
+
+    #[error("{path} was listed as a {old} before and now as a {new}, keeping the {old}")]
+    ConflictingKind {
+        path: Utf8PathBuf,
+        old: &'static str,
+        new: &'static str,
+    },
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+pub struct Interpretation {
+    pub fs: Filesystem,
+    pub warnings: Vec<Warning>,
+    // the lines that were skipped with `Strictness::Continue`
+    pub errors: Vec<InterpretError>,
+}
+
+// splits the input into lines while keeping their location
+fn parse_lines(i: Span) -> IResult<Span, Vec<Span>, ErrorTree<Span>> {
+    let mut lines = vec![];
+    let mut i = i;
+
+    while !i.fragment().is_empty() {
+        let (next_i, line) = terminated(not_line_ending, opt(line_ending))(i)?;
+        lines.push(line);
+        i = next_i;
+    }
+
+    Ok((i, lines))
+}
+
+// `Stack` and `Alt` errors wrap the actual failures, this picks the
+// one that got the furthest into the line
+fn deepest_base_error(
+    e: ErrorTree<Span>,
+) -> (
+    Span,
+    BaseErrorKind<&'static str, Box<dyn std::error::Error + Send + Sync>>,
+) {
+    match e {
+        GenericErrorTree::Base { location, kind } => (location, kind),
+        GenericErrorTree::Stack { base, .. } => deepest_base_error(*base),
+        GenericErrorTree::Alt(alternatives) => alternatives
+            .into_iter()
+            .map(deepest_base_error)
+            .max_by_key(|(location, _)| location.location_offset())
+            .expect("alt errors always have at least one alternative"),
+    }
+}
+
+// `start` is a byte offset into the line
+fn error_at(line: Span, start: usize, len: usize, kind: InterpretErrorKind) -> InterpretError {
+    InterpretError {
+        line: line.location_line(),
+        column: line.fragment()[..start].chars().count() + 1,
+        bad_part: SourceSpan::new((line.location_offset() + start).into(), len.into()),
+        kind,
+    }
+}
+
+// A line that doesn't parse is either a command that doesn't exist, or
+// a command or entry with something wrong in it.
+fn parse_error(line: Span, e: ErrorTree<Span>) -> InterpretError {
+    if let Some(command) = line.fragment().strip_prefix("$ ") {
+        let command = command.trim_start();
+        let name = command.split_whitespace().next().unwrap_or_default();
+        if !name.is_empty() && !["ls", "cd"].contains(&name) {
+            return error_at(
+                line,
+                line.fragment().len() - command.len(),
+                name.len(),
+                InterpretErrorKind::UnknownCommand(name.to_string()),
+            );
+        }
+    }
+
+    let (location, kind) = deepest_base_error(e);
+    error_at(
+        line,
+        location.location_offset() - line.location_offset(),
+        0,
+        InterpretErrorKind::Parse(kind.to_string()),
+    )
+}
+
+fn kind_name(is_dir: bool) -> &'static str {
+    if is_dir {
+        "directory"
+    } else {
+        "file"
+    }
+}
+
+struct Interpreter {
+    fs: Filesystem,
+    cwd: NodeId,
+    // whether the lines are the output of `ls` in `cwd`
+    listing: bool,
+    warnings: Vec<Warning>,
+}
+
+impl Interpreter {
+    // Like `Filesystem::resolve`, but directories that weren't seen yet
+    // are created: a transcript can `cd` into a directory before it was
+    // listed. Nothing changes unless the whole path resolves.
+    fn cd(&mut self, path: &Utf8Path) -> Result<(), InterpretErrorKind> {
+        let mut cursor = self.cwd.clone();
+        // the directories to create under `cursor`, in order
+        let mut staged: Vec<&str> = vec![];
+
+        for component in path.components() {
+            match component {
+                Utf8Component::Normal(name) if !staged.is_empty() => staged.push(name),
+                Utf8Component::Normal(name) => match self.fs.child(&cursor, name) {
+                    Some(id) if self.fs.entry(id).is_dir => cursor = id.clone(),
+                    Some(id) => return Err(PathError::NotADirectory(self.fs.path(id)).into()),
+                    None => staged.push(name),
+                },
+                Utf8Component::ParentDir if staged.pop().is_some() => {}
+                Utf8Component::CurDir => {}
+                _ => {
+                    staged.clear();
+                    cursor = self.fs.resolve(&cursor, component.as_str().into())?;
+                }
+            }
+        }
+
+        for name in staged {
+            cursor = self.fs.insert(
+                &cursor,
+                FsEntry {
+                    path: name.into(),
+                    size: 0,
+                    is_dir: true,
+                },
+            );
+        }
+        self.cwd = cursor;
+
+        Ok(())
+    }
+
+    // listing the same directory again only adds what's new
+    fn add_entry(&mut self, line: usize, entry: Entry) {
+        let new = match entry {
+            Entry::Dir(path) => FsEntry {
+                path,
+                size: 0,
+                is_dir: true,
+            },
+            Entry::File(size, path) => FsEntry {
+                path,
+                size,
+                is_dir: false,
+            },
+        };
+
+        let Some(id) = self.fs.child(&self.cwd, new.path.as_str()).cloned() else {
+            self.fs.insert(&self.cwd, new);
+            return;
+        };
+
+        let path = self.fs.path(&id);
+        let old = self.fs.entry_mut(&id);
+        let kind = if old.is_dir != new.is_dir {
+            WarningKind::ConflictingKind {
+                path,
+                old: kind_name(old.is_dir),
+                new: kind_name(new.is_dir),
+            }
+        } else if old.size != new.size {
+            let warning = WarningKind::ConflictingSize {
+                path,
+                old: old.size,
+                new: new.size,
+            };
+            old.size = new.size;
+            warning
+        } else {
+            return;
+        };
+
+        self.warnings.push(Warning { line, kind });
+    }
+
+    fn interpret_line(&mut self, line: Span) -> Result<(), InterpretError> {
+        let input_line = all_consuming(parse_input_line::<ErrorTree<Span>>)(line)
+            .finish()
+            .map_err(|e| parse_error(line, e))?
+            .1;
+        let line_error = |kind| error_at(line, 0, line.fragment().len(), kind);
+
+        match input_line {
+            InputLine::Command(Command::Ls) => self.listing = true,
+            InputLine::Command(Command::Cd(path)) => {
+                self.listing = false;
+                self.cd(&path).map_err(line_error)?;
+            }
+            InputLine::Entry(_) if !self.listing => {
+                return Err(line_error(InterpretErrorKind::EntryWithoutLs))
+            }
+            InputLine::Entry(Entry::Dir(name) | Entry::File(_, name))
+                if !is_entry_name(name.as_str()) =>
+            {
+                // the name is the end of the line
+                let len = name.as_str().len();
+                return Err(error_at(
+                    line,
+                    line.fragment().len() - len,
+                    len,
+                    InterpretErrorKind::BadName(name),
+                ));
+            }
+            InputLine::Entry(entry) => self.add_entry(line.location_line() as usize, entry),
+        }
+
+        Ok(())
+    }
+}
+
+// Rebuilds the filesystem from a transcript of `cd` and `ls` commands. The
+// transcript doesn't have to start with `cd /`, paths can be absolute or
+// have several parts, and directories can be listed more than once.
+pub fn interpret(input: &str, strictness: Strictness) -> Result<Interpretation, BadTranscript> {
+    let fs = Filesystem::new();
+    let mut interpreter = Interpreter {
+        cwd: fs.root().clone(),
+        fs,
+        listing: false,
+        warnings: vec![],
+    };
+    let mut errors = vec![];
+
+    let (_, lines) = parse_lines(Span::new(input))
+        .finish()
+        .expect("every input can be split into lines");
+
+    for line in lines {
+        if let Err(e) = interpreter.interpret_line(line) {
+            errors.push(e);
+
+            if strictness == Strictness::Stop {
+                return Err(BadTranscript {
+                    src: input.to_string(),
+                    errors,
+                });
+            }
+        }
+    }
+
+    Ok(Interpretation {
+        fs: interpreter.fs,
+        warnings: interpreter.warnings,
+        errors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{interpret, InterpretErrorKind, Strictness, WarningKind};
+    use crate::filesystem::PathError;
+    use crate::total_size;
+    use test_case::test_case;
+
+    #[test_case(include_str!("../input_small.txt"), 48381165)]
+    #[test_case(include_str!("../input.txt"), 46876531)]
+    fn test_total_size(input: &str, size: u64) {
+        let fs = interpret(input, Strictness::Stop).unwrap().fs;
+
+        assert_eq!(size, total_size(&fs.tree, fs.node(fs.root())).unwrap());
+    }
+
+    fn paths(input: &str) -> Vec<String> {
+        let fs = interpret(input, Strictness::Stop).unwrap().fs;
+
+        fs.tree
+            .traverse_pre_order_ids(fs.root())
+            .unwrap()
+            .map(|id| fs.path(&id).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_paths() {
+        let input = "$ cd /a/b
+$ ls
+1 x
+$ cd ../../c/./d
+$ ls
+2 y
+$ cd /
+$ ls
+dir a
+dir c";
+
+        assert_eq!(
+            vec!["/", "/a", "/a/b", "/a/b/x", "/c", "/c/d", "/c/d/y"],
+            paths(input)
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        let input = "$ ls
+dir My Documents
+123  Report-2022_v2.PDF
+$ cd My Documents
+$ ls
+7 ünïcödé.txt";
+
+        assert_eq!(
+            vec![
+                "/",
+                "/My Documents",
+                "/My Documents/ünïcödé.txt",
+                "/Report-2022_v2.PDF"
+            ],
+            paths(input)
+        );
+    }
+
+    #[test]
+    fn test_repeated_ls() {
+        let input = "$ ls
+dir a
+10 b
+$ ls
+dir a
+10 b
+$ ls
+dir a
+12 b
+$ ls
+10 a";
+        let interpretation = interpret(input, Strictness::Stop).unwrap();
+        let fs = &interpretation.fs;
+
+        assert_eq!(2, fs.children(fs.root()).count());
+        assert_eq!(
+            vec![
+                WarningKind::ConflictingSize {
+                    path: "/b".into(),
+                    old: 10,
+                    new: 12
+                },
+                WarningKind::ConflictingKind {
+                    path: "/a".into(),
+                    old: "directory",
+                    new: "file"
+                },
+            ],
+            interpretation
+                .warnings
+                .into_iter()
+                .map(|warning| warning.kind)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test_case("$ cd /\n$ cd ..", 2, 1, InterpretErrorKind::Path(PathError::AboveRoot); "cd above root")]
+    #[test_case("$ cd a/../..", 1, 1, InterpretErrorKind::Path(PathError::AboveRoot); "cd above root in a path")]
+    #[test_case("$ ls\n1 a\n$ cd a", 3, 1, InterpretErrorKind::Path(PathError::NotADirectory("/a".into())); "cd into a file")]
+    #[test_case("$ cd a\n1 b", 2, 1, InterpretErrorKind::EntryWithoutLs; "entry without ls")]
+    #[test_case("$ ls\ndir ..", 2, 5, InterpretErrorKind::BadName("..".into()); "dir named parent")]
+    #[test_case("$ ls\ndir .", 2, 5, InterpretErrorKind::BadName(".".into()); "dir named current")]
+    #[test_case("$ ls\ndir a/b", 2, 5, InterpretErrorKind::BadName("a/b".into()); "dir with a slash")]
+    #[test_case("$ ls\n12 /etc", 2, 4, InterpretErrorKind::BadName("/etc".into()); "absolute file")]
+    #[test_case("$ ls\n$ rm -rf /", 2, 3, InterpretErrorKind::UnknownCommand("rm".into()); "unknown command")]
+    #[test_case("$ ls\ndir", 2, 4, InterpretErrorKind::Parse("expected a space or tab".into()); "dir without name")]
+    #[test_case("$ cd", 1, 5, InterpretErrorKind::Parse("expected a space or tab".into()); "cd without path")]
+    fn test_errors(input: &str, line: u32, column: usize, kind: InterpretErrorKind) {
+        let err = interpret(input, Strictness::Stop).err().unwrap();
+
+        assert_eq!(1, err.errors.len());
+        let err = &err.errors[0];
+        assert_eq!((line, column, &kind), (err.line, err.column, &err.kind));
+    }
+
+    #[test]
+    fn test_continue() {
+        let input = "$ ls
+10 a
+$ ls -la
+dir b
+$ cd b
+$ cd ..
+$ cd ..
+$ ls
+20 c";
+        let interpretation = interpret(input, Strictness::Continue).unwrap();
+
+        let errors: Vec<_> = interpretation
+            .errors
+            .iter()
+            .map(|e| (e.line, e.kind.clone()))
+            .collect();
+        assert_eq!(
+            vec![
+                (3, InterpretErrorKind::Parse("expected eof".into())),
+                (7, InterpretErrorKind::Path(PathError::AboveRoot)),
+            ],
+            errors
+        );
+
+        // the failed `cd ..` left the interpreter in `/`
+        let fs = &interpretation.fs;
+        let names: Vec<_> = fs.children(fs.root()).map(|(name, _)| name).collect();
+        assert_eq!(vec!["a", "b", "c"], names);
+    }
+
+    #[test]
+    fn test_continue_after_bad_names() {
+        let input = "$ ls
+dir ..
+dir .
+dir a/b
+1 c";
+        let interpretation = interpret(input, Strictness::Continue).unwrap();
+
+        assert_eq!(
+            vec![2, 3, 4],
+            interpretation
+                .errors
+                .iter()
+                .map(|e| e.line)
+                .collect::<Vec<_>>()
+        );
+        let fs = &interpretation.fs;
+        let names: Vec<_> = fs.children(fs.root()).map(|(name, _)| name).collect();
+        assert_eq!(vec!["c"], names);
+    }
+
+    #[test]
+    fn test_continue_after_cd_through_a_file() {
+        let input = "$ cd d
+$ ls
+1 f
+$ cd f/x
+$ ls
+2 g";
+        let interpretation = interpret(input, Strictness::Continue).unwrap();
+
+        assert_eq!(
+            vec![InterpretErrorKind::Path(PathError::NotADirectory(
+                "/d/f".into()
+            ))],
+            interpretation
+                .errors
+                .into_iter()
+                .map(|e| e.kind)
+                .collect::<Vec<_>>()
+        );
+        // `g` is listed in `/d`, where the failed `cd` left the interpreter
+        let fs = &interpretation.fs;
+        let paths: Vec<_> = fs
+            .tree
+            .traverse_pre_order_ids(fs.root())
+            .unwrap()
+            .map(|id| fs.path(&id).to_string())
+            .collect();
+        assert_eq!(vec!["/", "/d", "/d/f", "/d/g"], paths);
+    }
+
+    #[test]
+    fn test_continue_after_cd_above_root_through_new_directories() {
+        let input = "$ cd x/y/../../..
+$ ls
+1 a";
+        let interpretation = interpret(input, Strictness::Continue).unwrap();
+
+        assert_eq!(1, interpretation.errors.len());
+        // neither `/x` nor `/x/y` were created
+        let fs = &interpretation.fs;
+        let names: Vec<_> = fs.children(fs.root()).map(|(name, _)| name).collect();
+        assert_eq!(vec!["a"], names);
+    }
+}
@@ -0,0 +1,125 @@
+use id_tree::Node;
+use id_tree::Tree;
+
+use camino::Utf8PathBuf;
+
+pub mod diff;
+pub mod du;
+pub mod filesystem;
+pub mod generate;
+pub mod interpret;
+pub mod materialize;
+pub mod planner;
+pub mod query;
+pub mod shell;
+pub mod treemap;
+
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_while1},
+    character::complete::{self as cc, space1},
+    combinator::map,
+    error::ParseError,
+    sequence::{pair, preceded, separated_pair},
+    IResult,
+};
+use nom_locate::LocatedSpan;
+
+pub type Span<'a> = LocatedSpan<&'a str>;
+
+// the rest of the line, names can have any character in them, even spaces
+pub fn parse_path<'a, E: ParseError<Span<'a>>>(i: Span<'a>) -> IResult<Span<'a>, Utf8PathBuf, E> {
+    map(
+        take_while1(|c: char| c != '\n' && c != '\r'),
+        |path: Span| path.fragment().into(),
+    )(i)
+}
+
+#[derive(Debug)]
+pub struct Ls;
+
+pub fn parse_ls<'a, E: ParseError<Span<'a>>>(i: Span<'a>) -> IResult<Span<'a>, Ls, E> {
+    map(tag("ls"), |_| Ls)(i)
+}
+
+#[derive(Debug)]
+pub struct Cd(pub Utf8PathBuf);
+
+pub fn parse_cd<'a, E: ParseError<Span<'a>>>(i: Span<'a>) -> IResult<Span<'a>, Cd, E> {
+    map(preceded(pair(tag("cd"), space1), parse_path), Cd)(i)
+}
+
+#[derive(Debug)]
+pub enum Command {
+    Ls,
+    Cd(Utf8PathBuf),
+}
+
+impl From<Ls> for Command {
+    fn from(_ls: Ls) -> Self {
+        Command::Ls
+    }
+}
+
+impl From<Cd> for Command {
+    fn from(cd: Cd) -> Self {
+        Command::Cd(cd.0)
+    }
+}
+
+pub fn parse_command<'a, E: ParseError<Span<'a>>>(i: Span<'a>) -> IResult<Span<'a>, Command, E> {
+    let (i, _) = tag("$ ")(i)?;
+
+    alt((map(parse_ls, Into::into), map(parse_cd, Into::into)))(i)
+}
+
+#[derive(Debug)]
+pub enum Entry {
+    Dir(Utf8PathBuf),
+    File(u64, Utf8PathBuf),
+}
+
+pub fn parse_entry<'a, E: ParseError<Span<'a>>>(i: Span<'a>) -> IResult<Span<'a>, Entry, E> {
+    let parse_file = map(
+        separated_pair(cc::u64, space1, parse_path),
+        |(size, path)| Entry::File(size, path),
+    );
+
+    let parse_dir = map(preceded(pair(tag("dir"), space1), parse_path), Entry::Dir);
+
+    alt((parse_file, parse_dir))(i)
+}
+
+#[derive(Debug)]
+pub enum InputLine {
+    Command(Command),
+    Entry(Entry),
+}
+
+pub fn parse_input_line<'a, E: ParseError<Span<'a>>>(
+    i: Span<'a>,
+) -> IResult<Span<'a>, InputLine, E> {
+    alt((
+        map(parse_command, InputLine::Command),
+        map(parse_entry, InputLine::Entry),
+    ))(i)
+}
+
+#[derive(Debug)]
+pub struct FsEntry {
+    // only the name, the full path is made from the names of the parents
+    pub path: Utf8PathBuf,
+    // 0 for directories, see `total_size`
+    pub size: u64,
+    // empty directories have no children either, so this can't be told
+    // from the tree alone
+    pub is_dir: bool,
+}
+
+pub fn total_size(tree: &Tree<FsEntry>, node: &Node<FsEntry>) -> color_eyre::Result<u64> {
+    let mut total = node.data().size;
+    for child in node.children() {
+        total += total_size(tree, tree.get(child)?)?;
+    }
+    Ok(total)
+}
@@ -1,200 +1,213 @@
-use id_tree::Node;
-use id_tree::Tree;
-
-use camino::Utf8PathBuf;
-use id_tree::InsertBehavior;
-
-use nom::{
-    branch::alt,
-    bytes::complete::tag,
-    bytes::complete::take_while1,
-    combinator::{all_consuming, map},
-    sequence::preceded,
-    sequence::separated_pair,
-    IResult,
+use day_07::{
+    diff::diff,
+    du::{du, DuOptions, DuReport},
+    filesystem::Filesystem,
+    generate::{read_dir, transcript, Order},
+    interpret::{interpret, BadTranscript, Strictness},
+    materialize::materialize,
+    planner::{plan, Disk, Plan},
+    query::{find, Query},
+    shell::Shell,
+    treemap::{treemap, TreemapOptions},
 };
+use miette::GraphicalReportHandler;
 
-fn parse_path(i: &str) -> IResult<&str, Utf8PathBuf> {
-    map(
-        take_while1(|c: char| "abcdefghijklmnopqrstuvwxyz./".contains(c)),
-        Into::into,
-    )(i)
-}
-
-#[derive(Debug)]
-struct Ls;
-
-fn parse_ls(i: &str) -> IResult<&str, Ls> {
-    map(tag("ls"), |_| Ls)(i)
+fn print_report(report: &BadTranscript) {
+    let mut s = String::new();
+    GraphicalReportHandler::new()
+        .render_report(&mut s, report)
+        .unwrap();
+    println!("{s}");
 }
 
-#[derive(Debug)]
-struct Cd(Utf8PathBuf);
-
-fn parse_cd(i: &str) -> IResult<&str, Cd> {
-    map(preceded(tag("cd "), parse_path), Cd)(i)
+// the filesystem from a transcript, after showing what's wrong with it
+fn load(input: &str, strictness: Strictness) -> Option<Filesystem> {
+    let mut interpretation = match interpret(input, strictness) {
+        Ok(interpretation) => interpretation,
+        Err(report) => {
+            print_report(&report);
+            return None;
+        }
+    };
+    if !interpretation.errors.is_empty() {
+        print_report(&BadTranscript {
+            src: input.to_string(),
+            errors: std::mem::take(&mut interpretation.errors),
+        });
+    }
+    for warning in &interpretation.warnings {
+        println!("warning: {warning}");
+    }
+    Some(interpretation.fs)
 }
 
-#[derive(Debug)]
-enum Command {
-    Ls,
-    Cd(Utf8PathBuf),
-}
+fn main() -> color_eyre::Result<()> {
+    color_eyre::install().unwrap();
 
-impl From<Ls> for Command {
-    fn from(_ls: Ls) -> Self {
-        Command::Ls
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // stop at the first bad line instead of skipping them
+    let strictness = match args.iter().any(|arg| arg == "--strict") {
+        true => Strictness::Stop,
+        false => Strictness::Continue,
+    };
+    // e.g. `--capacity=100000000 --needed=30000000`
+    let mut disk = Disk::default();
+    for arg in &args {
+        if let Some(capacity) = arg.strip_prefix("--capacity=") {
+            disk.capacity = capacity.parse()?;
+        } else if let Some(needed_free) = arg.strip_prefix("--needed=") {
+            disk.needed_free = needed_free.parse()?;
+        }
     }
-}
 
-impl From<Cd> for Command {
-    fn from(cd: Cd) -> Self {
-        Command::Cd(cd.0)
+    let Some(fs) = load(include_str!("../input_small.txt"), strictness) else {
+        return Ok(());
+    };
+
+    // e.g. `du 1 -h`
+    let mut words = args.iter().filter(|arg| !arg.starts_with("--"));
+    match words.next().map(String::as_str) {
+        Some("du") => {
+            let mut options = DuOptions::default();
+            for word in words {
+                match word.as_str() {
+                    "-h" => options.human_readable = true,
+                    depth => options.max_depth = Some(depth.parse()?),
+                }
+            }
+            let report = DuReport {
+                entries: du(&fs, &fs.usage(), options),
+                options,
+            };
+            print!("{report}");
+        }
+        Some("plan") => print_plan(&plan(&fs, &fs.usage(), disk)?),
+        // e.g. `generate some/dir bfs`, or the puzzle's filesystem without a dir
+        Some("generate") => {
+            let mut order = Order::DepthFirst;
+            let mut dir = None;
+            for word in words {
+                match word.as_str() {
+                    "bfs" => order = Order::BreadthFirst,
+                    path => dir = Some(read_dir(path.into())?),
+                }
+            }
+            print!("{}", transcript(&dir.as_ref().unwrap_or(&fs).tree, order)?);
+        }
+        // e.g. `find type:dir path:/a/** size>50k has:*.txt`
+        Some("find") => {
+            // the shell already split and unquoted the arguments
+            let query = Query::from_words(words.map(String::as_str))?;
+            for found in find(&fs, &fs.usage(), &query) {
+                println!("{found}");
+            }
+        }
+        // e.g. `diff before.txt after.txt --json`
+        Some("diff") => {
+            let mut transcripts = vec![];
+            for path in words {
+                match load(&std::fs::read_to_string(path)?, strictness) {
+                    Some(fs) => transcripts.push(fs),
+                    None => return Ok(()),
+                }
+            }
+            let [old, new] = &transcripts[..] else {
+                color_eyre::eyre::bail!("diff needs two transcripts");
+            };
+            let diff = diff(old, new);
+            match args.iter().any(|arg| arg == "--json") {
+                true => println!("{}", serde_json::to_string_pretty(&diff)?),
+                false => print!("{diff}"),
+            }
+        }
+        // e.g. `shell some/transcript.txt`, or the puzzle's without a transcript
+        Some("shell") => {
+            let fs = match words.next() {
+                Some(path) => match load(&std::fs::read_to_string(path)?, strictness) {
+                    Some(fs) => fs,
+                    None => return Ok(()),
+                },
+                None => fs,
+            };
+            Shell::new(fs).run(std::io::stdin().lock(), std::io::stdout())?;
+        }
+        // e.g. `treemap > treemap.svg`, with what `plan` would delete highlighted
+        Some("treemap") => {
+            let usage = fs.usage();
+            let plan = plan(&fs, &usage, disk)?;
+            let options = TreemapOptions {
+                highlight: [plan.single, plan.set]
+                    .into_iter()
+                    .flatten()
+                    .flat_map(|deletion| deletion.dirs)
+                    .collect(),
+                ..Default::default()
+            };
+            println!("{}", treemap(&fs, &usage, &options));
+        }
+        // e.g. `materialize /tmp/day_07`, then `du -sb --apparent-size /tmp/day_07`
+        Some("materialize") => {
+            let target = words
+                .next()
+                .ok_or_else(|| color_eyre::eyre::eyre!("materialize needs a target dir"))?;
+            materialize(&fs.tree, target.as_str().into())?;
+            let usage = fs.usage()[fs.root()];
+            println!(
+                "{target}: {} bytes in {} files, not counting directories",
+                usage.size, usage.files
+            );
+        }
+        _ => solve(&fs, disk)?,
     }
-}
-
-fn parse_command(i: &str) -> IResult<&str, Command> {
-    let (i, _) = tag("$ ")(i)?;
 
-    alt((map(parse_ls, Into::into), map(parse_cd, Into::into)))(i)
-}
-
-#[derive(Debug)]
-enum Entry {
-    Dir(Utf8PathBuf),
-    File(u64, Utf8PathBuf),
+    Ok(())
 }
 
-fn parse_entry(i: &str) -> IResult<&str, Entry> {
-    let parse_file = map(
-        separated_pair(nom::character::complete::u64, tag(" "), parse_path),
-        |(size, path)| Entry::File(size, path),
+fn print_plan(plan: &Plan) {
+    println!(
+        "used: {}, free: {}, to free: {}",
+        plan.used, plan.free, plan.to_free
     );
 
-    let parse_dir = map(preceded(tag("dir "), parse_path), Entry::Dir);
-
-    alt((parse_file, parse_dir))(i)
-}
-
-#[derive(Debug)]
-enum InputLine {
-    Command(Command),
-    Entry(Entry),
-}
-
-fn parse_input_line(i: &str) -> IResult<&str, InputLine> {
-    alt((
-        map(parse_command, InputLine::Command),
-        map(parse_entry, InputLine::Entry),
-    ))(i)
-}
-
-#[derive(Debug)]
-struct FsEntry {
-    path: Utf8PathBuf,
-    size: u64,
-}
-
-fn total_size(tree: &Tree<FsEntry>, node: &Node<FsEntry>) -> color_eyre::Result<u64> {
-    let mut total = node.data().size;
-    for child in node.children() {
-        total += total_size(tree, tree.get(child)?)?;
+    for (name, deletion) in [("single directory", &plan.single), ("set", &plan.set)] {
+        match deletion {
+            Some(deletion) => println!(
+                "{name}: delete {} to free {}, leaving {} free",
+                deletion
+                    .dirs
+                    .iter()
+                    .map(|dir| dir.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                deletion.freed,
+                deletion.free_after
+            ),
+            None => println!("{name}: not possible"),
+        }
     }
-    Ok(total)
 }
 
-fn main() -> color_eyre::Result<()> {
-    color_eyre::install().unwrap();
-
-    let lines = include_str!("../input_small.txt")
-        .lines()
-        .map(|line| all_consuming(parse_input_line)(line).unwrap().1);
-
-    let mut tree = Tree::<FsEntry>::new();
-
-    let root = tree.insert(
-        Node::new(FsEntry {
-            path: "/".into(),
-            size: 0,
-        }),
-        InsertBehavior::AsRoot,
-    )?;
-
-    let mut current_node = root;
-
-    for line in lines {
-        println!("line: {:?}", line);
-
-        match line {
-            InputLine::Command(cmd) => match cmd {
-                Command::Ls => {
-                    println!("ls");
-                }
-                Command::Cd(path) => match path.as_str() {
-                    "/" => {
-                        // ignore, we are already at the root and
-                        // for this exercise there is only one of these
-                        // commands at the beginning of the input so
-                        // ignoring it works fine.
-                    }
-                    ".." => {
-                        current_node = tree.get(&current_node)?.parent().unwrap().clone();
-                    }
-                    _ => {
-                        let node = Node::new(FsEntry {
-                            path: path.clone(),
-                            size: 0,
-                        });
-                        current_node =
-                            tree.insert(node, InsertBehavior::UnderNode(&current_node))?;
-                    }
-                },
-            },
-            InputLine::Entry(entry) => match entry {
-                Entry::Dir(_) => {
-                    // ignore, this is being handled when processing the "cd" command
-                }
-                Entry::File(size, path) => {
-                    let node = Node::new(FsEntry { size, path });
-                    tree.insert(node, InsertBehavior::UnderNode(&current_node))?;
-                }
-            },
-        }
-    }
+fn solve(fs: &Filesystem, disk: Disk) -> color_eyre::Result<()> {
+    let tree = &fs.tree;
 
     let mut s = String::new();
     tree.write_formatted(&mut s)?;
     println!("Tree:\n{s}");
 
-    let sum = tree
-        .traverse_pre_order(tree.root_node_id().unwrap())?
-        .filter(|node| !node.children().is_empty())
-        .map(|node| total_size(&tree, node).unwrap())
-        .filter(|&size| size <= 100_000)
+    let usage = fs.usage();
+    let sum = find(fs, &usage, &"type:dir size<=100000".parse()?)
+        .iter()
+        .map(|found| found.size)
         .inspect(|s| {
             dbg!(s);
         })
         .sum::<u64>();
     dbg!(sum);
 
-    let total_space = 70000000_u64;
-    let needed_free_space = 30000000_u64;
-
-    let used_space = total_size(&tree, tree.get(tree.root_node_id().unwrap())?)?;
-    let free_space = total_space.checked_sub(dbg!(used_space)).unwrap();
-
-    let minimum_space_to_free = needed_free_space.checked_sub(free_space).unwrap();
-
-    let size_to_remove = tree
-        .traverse_pre_order(tree.root_node_id().unwrap())?
-        .filter(|node| !node.children().is_empty())
-        .map(|node| total_size(&tree, node).unwrap())
-        .filter(|&size| size >= minimum_space_to_free)
-        .inspect(|s| {
-            dbg!(s);
-        })
-        .min();
+    let plan = plan(fs, &usage, disk)?;
+    dbg!(plan.used);
 
+    let size_to_remove = plan.single.map(|deletion| deletion.freed);
     dbg!(size_to_remove);
 
     Ok(())
@@ -0,0 +1,172 @@
+use std::fs::{self, OpenOptions};
+use std::io;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use id_tree::{NodeId, Tree};
+
+use crate::filesystem::is_entry_name;
+use crate::FsEntry;
+
+#[derive(thiserror::Error, Debug)]
+pub enum MaterializeError {
+    #[error("{path}: {source}")]
+    Io {
+        path: Utf8PathBuf,
+        source: io::Error,
+    },
+
+    #[error("{0}: the target has to be empty")]
+    NotEmpty(Utf8PathBuf),
+
+    // a name like `..` or `a/b`, which would end up somewhere else
+    #[error("{0:?}: this name would be written outside of its directory")]
+    BadName(Utf8PathBuf),
+}
+
+fn io_error(path: &Utf8Path) -> impl FnOnce(io::Error) -> MaterializeError {
+    let path = path.to_owned();
+    move |source| MaterializeError::Io { path, source }
+}
+
+// the path of `name` in `dir`, if it's really in `dir`
+fn join(dir: &Utf8Path, name: &Utf8Path) -> Option<Utf8PathBuf> {
+    is_entry_name(name.as_str()).then(|| dir.join(name))
+}
+
+// Writes the tree out under `target`, which becomes `/`. Files are sparse,
+// they have their size but take next to no space, so a `du
+// --apparent-size` or `find -size` on `target` can be compared with
+// `Filesystem::usage`.
+//
+// `target` is created if needed and has to be empty, nothing that's already
+// there is overwritten. Every name has to be a single normal path
+// component, nothing is written outside of `target`.
+pub fn materialize(tree: &Tree<FsEntry>, target: &Utf8Path) -> Result<(), MaterializeError> {
+    fn walk(tree: &Tree<FsEntry>, dir: &NodeId, path: &Utf8Path) -> Result<(), MaterializeError> {
+        for child in tree.children_ids(dir).unwrap() {
+            let entry = tree.get(child).unwrap().data();
+            let child_path = join(path, &entry.path)
+                .ok_or_else(|| MaterializeError::BadName(entry.path.clone()))?;
+
+            if entry.is_dir {
+                fs::create_dir(&child_path).map_err(io_error(&child_path))?;
+                walk(tree, child, &child_path)?;
+            } else {
+                OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&child_path)
+                    .and_then(|file| file.set_len(entry.size))
+                    .map_err(io_error(&child_path))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fs::create_dir_all(target).map_err(io_error(target))?;
+    if target
+        .read_dir_utf8()
+        .map_err(io_error(target))?
+        .next()
+        .is_some()
+    {
+        return Err(MaterializeError::NotEmpty(target.to_owned()));
+    }
+
+    match tree.root_node_id() {
+        Some(root) => walk(tree, root, target),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{materialize, MaterializeError};
+    use crate::filesystem::Filesystem;
+    use crate::generate::read_dir;
+    use crate::interpret::{interpret, Strictness};
+    use crate::FsEntry;
+    use camino::Utf8PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use test_case::test_case;
+
+    // a different one for every call, tests run in parallel
+    fn target(name: &str) -> Utf8PathBuf {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let count = COUNT.fetch_add(1, Ordering::Relaxed);
+        let dir =
+            std::env::temp_dir().join(format!("day_07-{name}-{}-{count}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.try_into().unwrap()
+    }
+
+    #[test_case(include_str!("../input_small.txt"), "materialize-small")]
+    #[test_case(include_str!("../input.txt"), "materialize-input")]
+    fn test_materialize(input: &str, name: &str) {
+        let fs = interpret(input, Strictness::Stop).unwrap().fs;
+        let target = target(name);
+
+        materialize(&fs.tree, &target).unwrap();
+        let read = read_dir(&target);
+        std::fs::remove_dir_all(&target).unwrap();
+        let read = read.unwrap();
+
+        // entries come back sorted by name
+        let sizes = |fs: &Filesystem| {
+            let usage = fs.usage();
+            let mut sizes: Vec<_> = fs
+                .tree
+                .traverse_pre_order_ids(fs.root())
+                .unwrap()
+                .map(|id| (fs.path(&id), usage[&id].size))
+                .collect();
+            sizes.sort();
+            sizes
+        };
+        assert_eq!(sizes(&fs), sizes(&read));
+    }
+
+    #[test_case(".."; "parent")]
+    #[test_case("a/../../b"; "nested parent")]
+    #[test_case("/etc"; "absolute")]
+    #[test_case("a/b"; "nested")]
+    #[test_case("."; "current")]
+    fn test_bad_name(name: &str) {
+        let mut fs = Filesystem::new();
+        let root = fs.root().clone();
+        fs.insert(
+            &root,
+            FsEntry {
+                path: name.into(),
+                size: 1,
+                is_dir: false,
+            },
+        );
+        let target = target("materialize-bad-name");
+
+        let result = materialize(&fs.tree, &target);
+        let written = target.read_dir_utf8().unwrap().count();
+        std::fs::remove_dir_all(&target).unwrap();
+
+        assert!(matches!(result, Err(MaterializeError::BadName(bad)) if bad == name));
+        assert_eq!(0, written);
+    }
+
+    #[test]
+    fn test_not_empty() {
+        let fs = interpret(include_str!("../input_small.txt"), Strictness::Stop)
+            .unwrap()
+            .fs;
+        let target = target("materialize-not-empty");
+        std::fs::create_dir_all(&target).unwrap();
+        std::fs::write(target.join("b.txt"), "keep").unwrap();
+
+        let result = materialize(&fs.tree, &target);
+        let kept = std::fs::read_to_string(target.join("b.txt")).unwrap();
+        std::fs::remove_dir_all(&target).unwrap();
+
+        assert!(matches!(result, Err(MaterializeError::NotEmpty(_))));
+        assert_eq!("keep", kept);
+    }
+}
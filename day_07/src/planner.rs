@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+
+use camino::Utf8PathBuf;
+use id_tree::NodeId;
+
+use crate::filesystem::{Filesystem, Usage};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Disk {
+    pub capacity: u64,
+    // how much has to be free for the update
+    pub needed_free: u64,
+}
+
+impl Default for Disk {
+    // the numbers from the puzzle
+    fn default() -> Self {
+        Disk {
+            capacity: 70_000_000,
+            needed_free: 30_000_000,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum PlanError {
+    #[error("{used} bytes are used, but the disk only holds {capacity}")]
+    OverCapacity { used: u64, capacity: u64 },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Deletion {
+    pub dirs: Vec<Utf8PathBuf>,
+    pub freed: u64,
+    pub free_after: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Plan {
+    pub used: u64,
+    pub free: u64,
+    // 0 if there's enough free space already
+    pub to_free: u64,
+    // None if no directory other than `/` is large enough on its own
+    pub single: Option<Deletion>,
+    // None if deleting everything but `/` and the files in it isn't enough
+    pub set: Option<Deletion>,
+}
+
+// a directory that can be deleted, with the range of pre-order indexes of
+// the directories in it, itself included
+struct Candidate {
+    id: NodeId,
+    size: u64,
+    start: usize,
+    end: usize,
+}
+
+impl Candidate {
+    fn nested(&self, other: &Candidate) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+}
+
+fn candidates(fs: &Filesystem, usage: &HashMap<NodeId, Usage>) -> Vec<Candidate> {
+    fn walk(
+        fs: &Filesystem,
+        usage: &HashMap<NodeId, Usage>,
+        dir: &NodeId,
+        candidates: &mut Vec<Candidate>,
+    ) {
+        for (_, child) in fs.children(dir) {
+            if !fs.entry(child).is_dir {
+                continue;
+            }
+
+            let index = candidates.len();
+            candidates.push(Candidate {
+                id: child.clone(),
+                size: usage[child].size,
+                start: index,
+                end: index,
+            });
+            walk(fs, usage, child, candidates);
+            candidates[index].end = candidates.len();
+        }
+    }
+
+    let mut candidates = vec![];
+    walk(fs, usage, fs.root(), &mut candidates);
+    candidates
+}
+
+// Finds the `k` directories that free the least while still freeing
+// `to_free`, going through the candidates from the largest. The search
+// stops early when even the next largest ones can't free enough anymore.
+fn fewest(candidates: &[&Candidate], k: usize, to_free: u64) -> Option<(u64, Vec<usize>)> {
+    fn search(
+        candidates: &[&Candidate],
+        from: usize,
+        chosen: &mut Vec<usize>,
+        freed: u64,
+        k: usize,
+        to_free: u64,
+        best: &mut Option<(u64, Vec<usize>)>,
+    ) {
+        let left = k - chosen.len();
+        if left == 0 {
+            let better = !matches!(best, Some((best, _)) if freed >= *best);
+            if freed >= to_free && better {
+                *best = Some((freed, chosen.clone()));
+            }
+            return;
+        }
+
+        for i in from..candidates.len() {
+            let at_most = freed
+                + candidates[i..]
+                    .iter()
+                    .take(left)
+                    .map(|candidate| candidate.size)
+                    .sum::<u64>();
+            if at_most < to_free {
+                break;
+            }
+
+            let candidate = candidates[i];
+            if chosen.iter().any(|&j| candidates[j].nested(candidate)) {
+                continue;
+            }
+
+            chosen.push(i);
+            search(
+                candidates,
+                i + 1,
+                chosen,
+                freed + candidate.size,
+                k,
+                to_free,
+                best,
+            );
+            chosen.pop();
+        }
+    }
+
+    let mut best = None;
+    search(candidates, 0, &mut vec![], 0, k, to_free, &mut best);
+    best
+}
+
+// Plans how to free enough space, by deleting either one directory or
+// several that are not inside one another. `/` itself is never deleted.
+//
+// For the set, as few directories as possible are deleted, and of those
+// the ones that free the least. Freeing the least with any number of
+// directories is a subset sum problem, which gets too slow for inputs like
+// the puzzle's.
+pub fn plan(
+    fs: &Filesystem,
+    usage: &HashMap<NodeId, Usage>,
+    disk: Disk,
+) -> Result<Plan, PlanError> {
+    let used = usage[fs.root()].size;
+    let free = disk
+        .capacity
+        .checked_sub(used)
+        .ok_or(PlanError::OverCapacity {
+            used,
+            capacity: disk.capacity,
+        })?;
+    let to_free = disk.needed_free.saturating_sub(free);
+
+    let deletion = |ids: Vec<&NodeId>| {
+        let freed = ids.iter().map(|&id| usage[id].size).sum::<u64>();
+        Deletion {
+            dirs: ids.into_iter().map(|id| fs.path(id)).collect(),
+            freed,
+            free_after: free + freed,
+        }
+    };
+
+    if to_free == 0 {
+        return Ok(Plan {
+            used,
+            free,
+            to_free,
+            single: Some(deletion(vec![])),
+            set: Some(deletion(vec![])),
+        });
+    }
+
+    let candidates = candidates(fs, usage);
+
+    let single = candidates
+        .iter()
+        .filter(|candidate| candidate.size >= to_free)
+        .min_by_key(|candidate| candidate.size)
+        .map(|candidate| deletion(vec![&candidate.id]));
+
+    let mut by_size: Vec<&Candidate> = candidates.iter().collect();
+    by_size.sort_by_key(|candidate| std::cmp::Reverse(candidate.size));
+
+    // every candidate is in one of the directories in `/`, so deleting all
+    // of those is the most that can be freed
+    let top_level = candidates
+        .iter()
+        .filter(|candidate| fs.parent(&candidate.id) == Some(fs.root()));
+    let most = top_level
+        .clone()
+        .map(|candidate| candidate.size)
+        .sum::<u64>();
+    let set = if most >= to_free {
+        (1..=top_level.count())
+            .find_map(|k| fewest(&by_size, k, to_free))
+            .map(|(_, chosen)| deletion(chosen.into_iter().map(|i| &by_size[i].id).collect()))
+    } else {
+        None
+    };
+
+    Ok(Plan {
+        used,
+        free,
+        to_free,
+        single,
+        set,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{plan, Disk, PlanError};
+    use crate::interpret::{interpret, Strictness};
+    use test_case::test_case;
+
+    const SMALL: &str = include_str!("../input_small.txt");
+
+    fn dirs(dirs: &[&str]) -> Vec<camino::Utf8PathBuf> {
+        dirs.iter().map(|&dir| dir.into()).collect()
+    }
+
+    #[test]
+    fn test_puzzle_disk() {
+        let fs = interpret(SMALL, Strictness::Stop).unwrap().fs;
+
+        let plan = plan(&fs, &fs.usage(), Disk::default()).unwrap();
+
+        assert_eq!(8381165, plan.to_free);
+        let single = plan.single.unwrap();
+        assert_eq!(dirs(&["/d"]), single.dirs);
+        assert_eq!(24933642, single.freed);
+        assert_eq!(plan.free + 24933642, single.free_after);
+        assert_eq!(Some(single), plan.set);
+    }
+
+    #[test]
+    fn test_set_of_dirs() {
+        let fs = interpret(SMALL, Strictness::Stop).unwrap().fs;
+
+        // 25000000 has to be freed, more than /d alone
+        let disk = Disk {
+            capacity: 48381165,
+            needed_free: 25_000_000,
+        };
+        let plan = plan(&fs, &fs.usage(), disk).unwrap();
+
+        assert_eq!(None, plan.single);
+        let set = plan.set.unwrap();
+        assert_eq!(dirs(&["/d", "/a"]), set.dirs);
+        assert_eq!(24933642 + 94853, set.freed);
+    }
+
+    #[test_case(48381165, 48381165; "only the files in / would be left")]
+    #[test_case(48381165, 30_000_000; "more than all dirs in /")]
+    fn test_impossible(capacity: u64, needed_free: u64) {
+        let fs = interpret(SMALL, Strictness::Stop).unwrap().fs;
+
+        let plan = plan(
+            &fs,
+            &fs.usage(),
+            Disk {
+                capacity,
+                needed_free,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(None, plan.single);
+        assert_eq!(None, plan.set);
+    }
+
+    #[test]
+    fn test_enough_free_already() {
+        let fs = interpret(SMALL, Strictness::Stop).unwrap().fs;
+
+        let plan = plan(
+            &fs,
+            &fs.usage(),
+            Disk {
+                capacity: 100_000_000,
+                needed_free: 30_000_000,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(0, plan.to_free);
+        assert!(plan.set.unwrap().dirs.is_empty());
+    }
+
+    #[test]
+    fn test_over_capacity() {
+        let fs = interpret(SMALL, Strictness::Stop).unwrap().fs;
+
+        assert_eq!(
+            Err(PlanError::OverCapacity {
+                used: 48381165,
+                capacity: 1000
+            }),
+            plan(
+                &fs,
+                &fs.usage(),
+                Disk {
+                    capacity: 1000,
+                    needed_free: 0
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_set_on_input() {
+        let fs = interpret(include_str!("../input.txt"), Strictness::Stop)
+            .unwrap()
+            .fs;
+        let usage = fs.usage();
+
+        // more than any directory in / holds on its own
+        let largest = fs
+            .children(fs.root())
+            .map(|(_, id)| usage[id].size)
+            .max()
+            .unwrap();
+        let disk = Disk {
+            capacity: usage[fs.root()].size,
+            needed_free: largest + 1,
+        };
+        let plan = plan(&fs, &usage, disk).unwrap();
+
+        assert_eq!(None, plan.single);
+        let set = plan.set.unwrap();
+        assert!(set.dirs.len() >= 2);
+        assert!(set.freed > largest);
+        for (i, a) in set.dirs.iter().enumerate() {
+            for b in &set.dirs[i + 1..] {
+                assert!(!a.starts_with(b) && !b.starts_with(a));
+            }
+        }
+    }
+}
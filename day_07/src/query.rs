@@ -0,0 +1,421 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use camino::Utf8PathBuf;
+use id_tree::NodeId;
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_while1},
+    character::complete::{self as cc, one_of},
+    combinator::{all_consuming, map, map_opt, opt, value},
+    sequence::{pair, preceded, tuple},
+    Finish, IResult,
+};
+
+use crate::filesystem::{Filesystem, Usage};
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    #[error("{0:?}: not a predicate, try e.g. `type:dir`, `path:/a/**`, `size>50k` or `depth<=2`")]
+    BadPredicate(String),
+
+    #[error("{0:?}: a quote is not closed")]
+    UnclosedQuote(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn holds(self, left: u64, right: u64) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Equal => left == right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Greater => left > right,
+        }
+    }
+}
+
+// A shell-like pattern: `*` is any number of characters other than `/`,
+// `**` any number of any characters and `?` one character other than `/`.
+// `/a/**` is everything under `/a`, but not `/a` itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Glob(pub String);
+
+impl Glob {
+    pub fn matches(&self, text: &str) -> bool {
+        fn matches(pattern: &[char], text: &[char]) -> bool {
+            match pattern {
+                [] => text.is_empty(),
+                ['*', '*', rest @ ..] => (0..=text.len()).any(|i| matches(rest, &text[i..])),
+                ['*', rest @ ..] => {
+                    let segment = text.iter().position(|&c| c == '/').unwrap_or(text.len());
+                    (0..=segment).any(|i| matches(rest, &text[i..]))
+                }
+                ['?', rest @ ..] => {
+                    matches!(text, [c, ..] if *c != '/') && matches(rest, &text[1..])
+                }
+                [c, rest @ ..] => text.first() == Some(c) && matches(rest, &text[1..]),
+            }
+        }
+
+        let pattern: Vec<char> = self.0.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        matches(&pattern, &text)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Predicate {
+    // `type:dir` or `type:file`
+    Type { is_dir: bool },
+    // `path:/a/**`, the full path
+    Path(Glob),
+    // `name:*.txt`
+    Name(Glob),
+    // `has:*.txt`, directories with an entry anywhere under them whose name
+    // matches
+    Has(Glob),
+    // `size>50k`, the total size for directories. `k`, `m` and `g` are
+    // powers of 1000.
+    Size(Comparison, u64),
+    // `depth<=1`, `/` is at depth 0
+    Depth(Comparison, u64),
+    // `!type:dir`
+    Not(Box<Predicate>),
+}
+
+fn parse_comparison(i: &str) -> IResult<&str, Comparison> {
+    alt((
+        value(Comparison::LessOrEqual, tag("<=")),
+        value(Comparison::GreaterOrEqual, tag(">=")),
+        value(Comparison::Less, tag("<")),
+        value(Comparison::Greater, tag(">")),
+        value(Comparison::Equal, tag("=")),
+    ))(i)
+}
+
+fn parse_size(i: &str) -> IResult<&str, u64> {
+    map_opt(pair(cc::u64, opt(one_of("kKmMgG"))), |(size, unit)| {
+        let unit = match unit.map(|unit| unit.to_ascii_lowercase()) {
+            Some('k') => 1_000,
+            Some('m') => 1_000_000,
+            Some('g') => 1_000_000_000,
+            _ => 1,
+        };
+        size.checked_mul(unit)
+    })(i)
+}
+
+fn parse_glob(i: &str) -> IResult<&str, Glob> {
+    map(take_while1(|_| true), |glob: &str| Glob(glob.to_string()))(i)
+}
+
+fn parse_predicate(i: &str) -> IResult<&str, Predicate> {
+    alt((
+        map(preceded(tag("!"), parse_predicate), |predicate| {
+            Predicate::Not(Box::new(predicate))
+        }),
+        value(Predicate::Type { is_dir: true }, tag("type:dir")),
+        value(Predicate::Type { is_dir: false }, tag("type:file")),
+        map(preceded(tag("path:"), parse_glob), Predicate::Path),
+        map(preceded(tag("name:"), parse_glob), Predicate::Name),
+        map(preceded(tag("has:"), parse_glob), Predicate::Has),
+        map(
+            tuple((tag("size"), parse_comparison, parse_size)),
+            |(_, comparison, size)| Predicate::Size(comparison, size),
+        ),
+        map(
+            tuple((tag("depth"), parse_comparison, cc::u64)),
+            |(_, comparison, depth)| Predicate::Depth(comparison, depth),
+        ),
+    ))(i)
+}
+
+// Splits a query into words like a shell does: whitespace in quotes or
+// after a `\` is part of the word, for names like `My Documents`. Quoting
+// doesn't make `*` or `?` match themselves, they're always wildcards.
+fn split_words(s: &str) -> Result<Vec<String>, QueryError> {
+    let mut words = vec![];
+    // `None` between words, a word can be empty if it's quoted
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            // nothing is escaped in single quotes
+            (None | Some('"'), '\\') => word
+                .get_or_insert_with(String::new)
+                .push(chars.next().unwrap_or('\\')),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (_, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if quote.is_some() {
+        return Err(QueryError::UnclosedQuote(s.to_string()));
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+// Predicates separated by whitespace, an entry has to match all of them,
+// e.g. `type:dir path:/a/** size>50k has:*.txt` or `name:"My Documents"`.
+// No predicates match everything.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Query(pub Vec<Predicate>);
+
+impl Query {
+    // for words that are already split, like command line arguments
+    pub fn from_words<'a>(words: impl IntoIterator<Item = &'a str>) -> Result<Query, QueryError> {
+        words
+            .into_iter()
+            .map(|word| {
+                all_consuming(parse_predicate)(word)
+                    .finish()
+                    .map(|(_, predicate)| predicate)
+                    .map_err(|_: nom::error::Error<&str>| {
+                        QueryError::BadPredicate(word.to_string())
+                    })
+            })
+            .collect::<Result<_, _>>()
+            .map(Query)
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Query::from_words(split_words(s)?.iter().map(String::as_str))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Found {
+    pub path: Utf8PathBuf,
+    pub size: u64,
+    pub is_dir: bool,
+    pub depth: usize,
+}
+
+// directories end with a `/`, like `ls -p` shows them
+impl fmt::Display for Found {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let slash = match self.is_dir && self.depth > 0 {
+            true => "/",
+            false => "",
+        };
+        write!(f, "{:>10} {}{slash}", self.size, self.path)
+    }
+}
+
+struct Candidate<'a> {
+    fs: &'a Filesystem,
+    id: &'a NodeId,
+    path: Utf8PathBuf,
+    size: u64,
+    depth: usize,
+}
+
+impl Candidate<'_> {
+    fn matches(&self, predicate: &Predicate) -> bool {
+        let entry = self.fs.entry(self.id);
+        match predicate {
+            Predicate::Type { is_dir } => entry.is_dir == *is_dir,
+            Predicate::Path(glob) => glob.matches(self.path.as_str()),
+            Predicate::Name(glob) => glob.matches(entry.path.as_str()),
+            Predicate::Has(glob) => {
+                entry.is_dir
+                    && self
+                        .fs
+                        .tree
+                        .traverse_pre_order(self.id)
+                        .expect("node ids are from this tree")
+                        .skip(1)
+                        .any(|node| glob.matches(node.data().path.as_str()))
+            }
+            Predicate::Size(comparison, size) => comparison.holds(self.size, *size),
+            Predicate::Depth(comparison, depth) => comparison.holds(self.depth as u64, *depth),
+            Predicate::Not(predicate) => !self.matches(predicate),
+        }
+    }
+}
+
+// the entries that match the query, `/` first and then the entries of every
+// directory in the order they were added, each followed by what's under it
+pub fn find(fs: &Filesystem, usage: &HashMap<NodeId, Usage>, query: &Query) -> Vec<Found> {
+    fn walk(
+        fs: &Filesystem,
+        usage: &HashMap<NodeId, Usage>,
+        query: &Query,
+        id: &NodeId,
+        depth: usize,
+        found: &mut Vec<Found>,
+    ) {
+        let candidate = Candidate {
+            fs,
+            id,
+            path: fs.path(id),
+            size: usage[id].size,
+            depth,
+        };
+        if query.0.iter().all(|predicate| candidate.matches(predicate)) {
+            found.push(Found {
+                path: candidate.path,
+                size: candidate.size,
+                is_dir: fs.entry(id).is_dir,
+                depth,
+            });
+        }
+
+        for (_, child) in fs.children(id) {
+            walk(fs, usage, query, child, depth + 1, found);
+        }
+    }
+
+    let mut found = vec![];
+    walk(fs, usage, query, fs.root(), 0, &mut found);
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find, Comparison, Glob, Predicate, Query, QueryError};
+    use crate::interpret::{interpret, Strictness};
+    use test_case::test_case;
+
+    #[test_case("*.txt", "b.txt", true)]
+    #[test_case("*.txt", "b.dat", false)]
+    #[test_case("/a/*", "/a/e", true)]
+    #[test_case("/a/*", "/a/e/i", false)]
+    #[test_case("/a/**", "/a/e/i", true)]
+    #[test_case("/a/**", "/a", false)]
+    #[test_case("/**/i", "/a/e/i", true)]
+    #[test_case("?.log", "d.log", true)]
+    #[test_case("?.log", "/.log", false)]
+    fn test_glob(glob: &str, text: &str, expected: bool) {
+        assert_eq!(expected, Glob(glob.to_string()).matches(text));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Ok(Query(vec![
+                Predicate::Type { is_dir: true },
+                Predicate::Path(Glob("/a/**".to_string())),
+                Predicate::Size(Comparison::Greater, 50_000),
+                Predicate::Not(Box::new(Predicate::Has(Glob("*.txt".to_string())))),
+                Predicate::Depth(Comparison::LessOrEqual, 2),
+            ])),
+            "type:dir path:/a/** size>50k !has:*.txt depth<=2".parse()
+        );
+    }
+
+    #[test_case(r#"name:"My Documents""#, "My Documents"; "double quotes")]
+    #[test_case("name:'My Documents'", "My Documents"; "single quotes")]
+    #[test_case(r"name:My\ Documents", "My Documents"; "backslash")]
+    #[test_case(r#"name:'a "b" \c'"#, r#"a "b" \c"#; "nothing escaped in single quotes")]
+    #[test_case(r#"name:"a \"b\"""#, r#"a "b""#; "escaped double quotes")]
+    fn test_parse_quoted(query: &str, name: &str) {
+        assert_eq!(
+            Ok(Query(vec![Predicate::Name(Glob(name.to_string()))])),
+            query.parse()
+        );
+    }
+
+    #[test_case("name:'a b"; "single quote")]
+    #[test_case(r#"type:dir name:"a\""#; "escaped closing quote")]
+    fn test_unclosed_quote(query: &str) {
+        assert_eq!(
+            Err(QueryError::UnclosedQuote(query.to_string())),
+            query.parse::<Query>()
+        );
+    }
+
+    #[test]
+    fn test_find_names_with_spaces() {
+        let input = "$ ls
+dir My Documents
+$ cd My Documents
+$ ls
+7 a b.txt
+8 a.txt";
+        let fs = interpret(input, Strictness::Stop).unwrap().fs;
+
+        let found = find(
+            &fs,
+            &fs.usage(),
+            &"path:'/My Documents/*' name:a\\ *".parse().unwrap(),
+        );
+
+        let paths: Vec<_> = found.iter().map(|found| found.path.as_str()).collect();
+        assert_eq!(vec!["/My Documents/a b.txt"], paths);
+    }
+
+    #[test_case("type:link")]
+    #[test_case("size>")]
+    #[test_case("size~5")]
+    #[test_case("depth<=2k")]
+    #[test_case("path:")]
+    #[test_case("size>99999999999999999g")]
+    fn test_parse_error(word: &str) {
+        assert_eq!(
+            Err(QueryError::BadPredicate(word.to_string())),
+            format!("type:dir {word}").parse::<Query>()
+        );
+    }
+
+    #[test_case("", &["/", "/a", "/a/e", "/a/e/i", "/a/f", "/a/g", "/a/h.lst", "/b.txt", "/c.dat", "/d", "/d/j", "/d/d.log", "/d/d.ext", "/d/k"])]
+    #[test_case("type:dir size<=100000", &["/a", "/a/e"])]
+    #[test_case("type:dir has:*.txt", &["/"])]
+    #[test_case("type:dir has:*.lst", &["/", "/a"])]
+    #[test_case("path:/d/** size>5m", &["/d/d.log", "/d/d.ext", "/d/k"])]
+    #[test_case("type:file depth=1", &["/b.txt", "/c.dat"])]
+    #[test_case("!type:dir name:?", &["/a/e/i", "/a/f", "/a/g", "/d/j", "/d/k"])]
+    fn test_find(query: &str, expected: &[&str]) {
+        let fs = interpret(include_str!("../input_small.txt"), Strictness::Stop)
+            .unwrap()
+            .fs;
+
+        let found = find(&fs, &fs.usage(), &query.parse().unwrap());
+
+        let paths: Vec<_> = found.iter().map(|found| found.path.as_str()).collect();
+        assert_eq!(expected, paths);
+    }
+
+    #[test]
+    fn test_part_one() {
+        let fs = interpret(include_str!("../input.txt"), Strictness::Stop)
+            .unwrap()
+            .fs;
+        let usage = fs.usage();
+
+        let found = find(&fs, &usage, &"type:dir size<=100000".parse().unwrap());
+
+        let expected = fs
+            .tree
+            .traverse_pre_order_ids(fs.root())
+            .unwrap()
+            .filter(|id| fs.entry(id).is_dir)
+            .map(|id| usage[&id].size)
+            .filter(|&size| size <= 100_000)
+            .sum::<u64>();
+        assert_eq!(expected, found.iter().map(|found| found.size).sum::<u64>());
+    }
+}
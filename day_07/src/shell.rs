@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, Write};
+
+use camino::Utf8PathBuf;
+use id_tree::NodeId;
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{self as cc, space1},
+    combinator::{all_consuming, map, opt, rest, value},
+    multi::many0,
+    sequence::preceded,
+    Finish, IResult,
+};
+
+use crate::du::{du, human_size, DuOptions};
+use crate::filesystem::{Filesystem, PathError, Usage};
+use crate::query::{find, Query, QueryError};
+use crate::{parse_cd, parse_entry, parse_ls, parse_path, Entry, FsEntry, Span};
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ShellError {
+    #[error("{0}: unknown command, try `help`")]
+    UnknownCommand(String),
+
+    #[error("{0}: there's already an entry with this name")]
+    Exists(Utf8PathBuf),
+
+    #[error(transparent)]
+    Path(#[from] PathError),
+
+    #[error(transparent)]
+    Query(#[from] QueryError),
+}
+
+#[derive(Debug)]
+enum ShellCommand {
+    Cd(Utf8PathBuf),
+    Ls(Option<Utf8PathBuf>),
+    Du(Vec<DuArg>),
+    Tree(Option<Utf8PathBuf>),
+    Find(String),
+    Pwd,
+    Help,
+    Exit,
+    // a line of `ls` output, adds the entry to the current directory
+    Entry(Entry),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum DuArg {
+    HumanReadable,
+    MaxDepth(usize),
+}
+
+const HELP: &str = "\
+cd PATH         change the current directory
+ls [PATH]       list a directory, like in the transcript
+du [-h] [DEPTH] the size of every directory under the current one
+tree [PATH]     everything under a directory, with sizes
+find QUERY      entries under the current directory, e.g. `find type:dir size>50k`
+pwd             print the current directory
+dir NAME        add a directory to the current one, like `ls` output
+SIZE NAME       add a file to the current directory
+exit            leave, like Ctrl-D
+";
+
+fn optional_path<'a>(i: Span<'a>) -> IResult<Span<'a>, Option<Utf8PathBuf>> {
+    opt(preceded(space1, parse_path))(i)
+}
+
+// The same syntax as in a transcript, with or without the `$ `, plus the
+// commands that aren't in transcripts. A line of `ls` output adds an entry.
+fn parse_shell_command(i: Span) -> IResult<Span, ShellCommand> {
+    let (i, _) = opt(tag("$ "))(i)?;
+
+    alt((
+        map(parse_cd, |cd| ShellCommand::Cd(cd.0)),
+        map(preceded(parse_ls, optional_path), ShellCommand::Ls),
+        map(
+            preceded(
+                tag("du"),
+                many0(preceded(
+                    space1,
+                    alt((
+                        value(DuArg::HumanReadable, tag("-h")),
+                        map(cc::u64, |depth| DuArg::MaxDepth(depth as usize)),
+                    )),
+                )),
+            ),
+            ShellCommand::Du,
+        ),
+        map(preceded(tag("tree"), optional_path), ShellCommand::Tree),
+        map(
+            preceded(tag("find"), opt(preceded(space1, rest))),
+            |query: Option<Span>| {
+                ShellCommand::Find(query.map(|query| query.to_string()).unwrap_or_default())
+            },
+        ),
+        map(tag("pwd"), |_| ShellCommand::Pwd),
+        map(tag("help"), |_| ShellCommand::Help),
+        map(alt((tag("exit"), tag("quit"))), |_| ShellCommand::Exit),
+        map(parse_entry, ShellCommand::Entry),
+    ))(i)
+}
+
+// A shell over a reconstructed filesystem, with `/` as the current
+// directory to begin with. Sizes are kept up to date when entries are
+// added.
+pub struct Shell {
+    fs: Filesystem,
+    cwd: NodeId,
+    usage: HashMap<NodeId, Usage>,
+}
+
+impl Shell {
+    pub fn new(fs: Filesystem) -> Self {
+        Shell {
+            cwd: fs.root().clone(),
+            usage: fs.usage(),
+            fs,
+        }
+    }
+
+    pub fn cwd(&self) -> Utf8PathBuf {
+        self.fs.path(&self.cwd)
+    }
+
+    // the directory a command is about, the current one if there's no path
+    fn dir(&self, path: &Option<Utf8PathBuf>) -> Result<NodeId, ShellError> {
+        Ok(match path {
+            Some(path) => self.fs.resolve(&self.cwd, path)?,
+            None => self.cwd.clone(),
+        })
+    }
+
+    fn write_tree(&self, id: &NodeId, depth: usize, out: &mut String) {
+        let entry = self.fs.entry(id);
+        let kind = match entry.is_dir {
+            true => "dir",
+            false => "file",
+        };
+        writeln!(
+            out,
+            "{:indent$}- {} ({kind}, size={})",
+            "",
+            entry.path,
+            self.usage[id].size,
+            indent = depth * 2
+        )
+        .unwrap();
+
+        for (_, child) in self.fs.children(id) {
+            self.write_tree(child, depth + 1, out);
+        }
+    }
+
+    // what the command prints
+    fn execute(&mut self, command: ShellCommand) -> Result<String, ShellError> {
+        let mut out = String::new();
+
+        match command {
+            ShellCommand::Cd(path) => {
+                let id = self.fs.resolve(&self.cwd, &path)?;
+                if !self.fs.entry(&id).is_dir {
+                    return Err(PathError::NotADirectory(self.fs.path(&id)).into());
+                }
+                self.cwd = id;
+            }
+            ShellCommand::Ls(path) => {
+                let dir = self.dir(&path)?;
+                if !self.fs.entry(&dir).is_dir {
+                    writeln!(out, "{} {}", self.usage[&dir].size, self.fs.path(&dir)).unwrap();
+                }
+                for (name, child) in self.fs.children(&dir) {
+                    match self.fs.entry(child).is_dir {
+                        true => writeln!(out, "dir {name}").unwrap(),
+                        false => writeln!(out, "{} {name}", self.fs.entry(child).size).unwrap(),
+                    }
+                }
+            }
+            ShellCommand::Du(args) => {
+                let cwd = self.cwd();
+                // `du` counts from `/`
+                let depth = cwd.components().count() - 1;
+                let mut options = DuOptions::default();
+                for arg in args {
+                    match arg {
+                        DuArg::HumanReadable => options.human_readable = true,
+                        DuArg::MaxDepth(max_depth) => options.max_depth = Some(depth + max_depth),
+                    }
+                }
+
+                for entry in du(&self.fs, &self.usage, options) {
+                    if !entry.path.starts_with(&cwd) {
+                        continue;
+                    }
+                    let size = match options.human_readable {
+                        true => human_size(entry.usage.size),
+                        false => entry.usage.size.to_string(),
+                    };
+                    writeln!(out, "{size}\t{}", entry.path).unwrap();
+                }
+            }
+            ShellCommand::Tree(path) => self.write_tree(&self.dir(&path)?, 0, &mut out),
+            ShellCommand::Find(query) => {
+                let query: Query = query.parse()?;
+                let cwd = self.cwd();
+                for found in find(&self.fs, &self.usage, &query) {
+                    if found.path.starts_with(&cwd) {
+                        writeln!(out, "{found}").unwrap();
+                    }
+                }
+            }
+            ShellCommand::Pwd => writeln!(out, "{}", self.cwd()).unwrap(),
+            ShellCommand::Help => out.push_str(HELP),
+            ShellCommand::Exit => {}
+            ShellCommand::Entry(entry) => {
+                let entry = match entry {
+                    Entry::Dir(path) => FsEntry {
+                        path,
+                        size: 0,
+                        is_dir: true,
+                    },
+                    Entry::File(size, path) => FsEntry {
+                        path,
+                        size,
+                        is_dir: false,
+                    },
+                };
+                if self.fs.child(&self.cwd, entry.path.as_str()).is_some() {
+                    return Err(ShellError::Exists(self.cwd().join(&entry.path)));
+                }
+                self.fs.insert(&self.cwd, entry);
+                self.usage = self.fs.usage();
+            }
+        }
+
+        Ok(out)
+    }
+
+    // Runs one line, errors in the command are written to `out` too. Returns
+    // whether to keep going, `false` after `exit`.
+    pub fn run_line(&mut self, line: &str, out: &mut impl Write) -> io::Result<bool> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(true);
+        }
+
+        let command = match all_consuming(parse_shell_command)(Span::new(line)).finish() {
+            Ok((_, command)) => command,
+            Err(_) => {
+                writeln!(
+                    out,
+                    "error: {}",
+                    ShellError::UnknownCommand(line.to_string())
+                )?;
+                return Ok(true);
+            }
+        };
+        if matches!(command, ShellCommand::Exit) {
+            return Ok(false);
+        }
+
+        match self.execute(command) {
+            Ok(printed) => write!(out, "{printed}")?,
+            Err(e) => writeln!(out, "error: {e}")?,
+        }
+        Ok(true)
+    }
+
+    // reads commands until `exit` or the end of the input, with a prompt
+    // that shows the current directory
+    pub fn run(&mut self, input: impl BufRead, mut out: impl Write) -> io::Result<()> {
+        let mut lines = input.lines();
+        loop {
+            write!(out, "{} $ ", self.cwd())?;
+            out.flush()?;
+
+            let Some(line) = lines.next() else {
+                writeln!(out)?;
+                return Ok(());
+            };
+            if !self.run_line(&line?, &mut out)? {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Shell;
+    use crate::interpret::{interpret, Strictness};
+    use test_case::test_case;
+
+    fn shell() -> Shell {
+        Shell::new(
+            interpret(include_str!("../input_small.txt"), Strictness::Stop)
+                .unwrap()
+                .fs,
+        )
+    }
+
+    fn run(shell: &mut Shell, lines: &[&str]) -> String {
+        let mut out = vec![];
+        for line in lines {
+            assert!(shell.run_line(line, &mut out).unwrap());
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test_case(&["ls"], "dir a\n14848514 b.txt\n8504156 c.dat\ndir d\n")]
+    #[test_case(&["$ cd a", "$ ls"], "dir e\n29116 f\n2557 g\n62596 h.lst\n")]
+    #[test_case(&["cd /a/e", "pwd", "cd ../..", "pwd"], "/a/e\n/\n")]
+    #[test_case(&["ls a/e"], "584 i\n")]
+    #[test_case(&["cd a", "tree"], "- a (dir, size=94853)\n  - e (dir, size=584)\n    - i (file, size=584)\n  - f (file, size=29116)\n  - g (file, size=2557)\n  - h.lst (file, size=62596)\n")]
+    #[test_case(&["du"], "48381165\t/\n24933642\t/d\n94853\t/a\n584\t/a/e\n")]
+    #[test_case(&["cd a", "du -h 0"], "93K\t/a\n")]
+    #[test_case(&["cd d", "find size>8m"], "  24933642 /d/\n   8033020 /d/d.log\n")]
+    fn test_commands(lines: &[&str], expected: &str) {
+        assert_eq!(expected, run(&mut shell(), lines));
+    }
+
+    #[test_case(&["cd x"], "error: /x: no such file or directory\n")]
+    #[test_case(&["cd b.txt"], "error: /b.txt: not a directory\n")]
+    #[test_case(&["cd .."], "error: there is nothing above /\n")]
+    #[test_case(&["rm -rf /"], "error: rm -rf /: unknown command, try `help`\n")]
+    #[test_case(&["find size>>1"], "error: \"size>>1\": not a predicate, try e.g. `type:dir`, `path:/a/**`, `size>50k` or `depth<=2`\n")]
+    #[test_case(&["dir a"], "error: /a: there's already an entry with this name\n")]
+    fn test_errors(lines: &[&str], expected: &str) {
+        assert_eq!(expected, run(&mut shell(), lines));
+    }
+
+    #[test]
+    fn test_add_entries() {
+        let mut shell = shell();
+
+        let out = run(
+            &mut shell,
+            &["cd a/e", "dir x", "cd x", "1000 y", "cd /", "du 1"],
+        );
+
+        assert_eq!("48382165\t/\n24933642\t/d\n95853\t/a\n", out);
+    }
+
+    #[test]
+    fn test_run() {
+        let mut out = vec![];
+
+        shell()
+            .run("cd a\nls e\nexit\nls\n".as_bytes(), &mut out)
+            .unwrap();
+
+        assert_eq!("/ $ /a $ 584 i\n/a $ ", String::from_utf8(out).unwrap());
+    }
+}
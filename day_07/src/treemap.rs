@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+
+use camino::Utf8PathBuf;
+use id_tree::NodeId;
+use svg::node::element::{Group, Rectangle, Text, Title};
+use svg::Document;
+
+use crate::du::human_size;
+use crate::filesystem::{Filesystem, Usage};
+
+// room for the label at the top of a directory, and around what's in it
+const HEADER: f64 = 14.0;
+const PADDING: f64 = 2.0;
+const FONT_SIZE: f64 = 10.0;
+// roughly how wide a character of the label is
+const CHAR_WIDTH: f64 = 6.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Rect {
+    fn area(&self) -> f64 {
+        self.width * self.height
+    }
+
+    // what's left inside the border, or `None` if that's nothing
+    fn shrink(&self, top: f64, sides: f64) -> Option<Rect> {
+        let rect = Rect {
+            x: self.x + sides,
+            y: self.y + top,
+            width: self.width - 2.0 * sides,
+            height: self.height - top - sides,
+        };
+        (rect.width > 0.0 && rect.height > 0.0).then_some(rect)
+    }
+}
+
+// the worst aspect ratio in a row of `areas` laid along a side of `length`
+fn worst(areas: &[f64], length: f64) -> f64 {
+    let sum: f64 = areas.iter().sum();
+    let max = areas.iter().cloned().fold(f64::MIN, f64::max);
+    let min = areas.iter().cloned().fold(f64::MAX, f64::min);
+    let length = length * length;
+    let sum = sum * sum;
+    (length * max / sum).max(sum / (length * min))
+}
+
+// Splits `rect` into rectangles with areas proportional to `sizes`, as square
+// as possible, following "Squarified Treemaps" by Bruls, Huizing and van
+// Wijk. `sizes` have to be positive and sorted from the largest, the
+// rectangles are in the same order.
+pub fn squarify(sizes: &[u64], rect: Rect) -> Vec<Rect> {
+    let total: u64 = sizes.iter().sum();
+    if total == 0 {
+        return vec![];
+    }
+    let areas: Vec<f64> = sizes
+        .iter()
+        .map(|&size| size as f64 / total as f64 * rect.area())
+        .collect();
+
+    let mut rects = Vec::with_capacity(sizes.len());
+    let mut free = rect;
+    let mut start = 0;
+    while start < areas.len() {
+        // rows go along the shorter side of what's left
+        let length = free.width.min(free.height);
+        let mut end = start + 1;
+        while end < areas.len()
+            && worst(&areas[start..=end], length) <= worst(&areas[start..end], length)
+        {
+            end += 1;
+        }
+
+        let row: f64 = areas[start..end].iter().sum();
+        if free.width >= free.height {
+            // a column on the left
+            let thickness = row / free.height;
+            let mut y = free.y;
+            for area in &areas[start..end] {
+                let height = area / thickness;
+                rects.push(Rect {
+                    x: free.x,
+                    y,
+                    width: thickness,
+                    height,
+                });
+                y += height;
+            }
+            free.x += thickness;
+            free.width -= thickness;
+        } else {
+            // a row at the top
+            let thickness = row / free.width;
+            let mut x = free.x;
+            for area in &areas[start..end] {
+                let width = area / thickness;
+                rects.push(Rect {
+                    x,
+                    y: free.y,
+                    width,
+                    height: thickness,
+                });
+                x += width;
+            }
+            free.y += thickness;
+            free.height -= thickness;
+        }
+
+        start = end;
+    }
+
+    rects
+}
+
+#[derive(Clone, Debug)]
+pub struct TreemapOptions {
+    pub width: f64,
+    pub height: f64,
+    // e.g. the directories from a `Plan`
+    pub highlight: Vec<Utf8PathBuf>,
+}
+
+impl Default for TreemapOptions {
+    fn default() -> Self {
+        TreemapOptions {
+            width: 1200.0,
+            height: 800.0,
+            highlight: vec![],
+        }
+    }
+}
+
+// names can have any character in them
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+struct Treemap<'a> {
+    fs: &'a Filesystem,
+    usage: &'a HashMap<NodeId, Usage>,
+    options: &'a TreemapOptions,
+}
+
+impl Treemap<'_> {
+    fn entry(&self, id: &NodeId, rect: Rect, depth: usize) -> Group {
+        let path = self.fs.path(id);
+        let size = self.usage[id].size;
+        let is_dir = self.fs.entry(id).is_dir;
+        let highlighted = self.options.highlight.contains(&path);
+
+        // directories get darker the deeper they are, files are lighter
+        let fill = match (highlighted, is_dir) {
+            (true, _) => "#ffc107".to_string(),
+            (false, true) => {
+                let shade = 230 - (depth * 25).min(150) as u8;
+                format!("rgb({shade}, {shade}, 255)")
+            }
+            (false, false) => "rgb(240, 240, 240)".to_string(),
+        };
+        let mut rectangle = Rectangle::new()
+            .set("x", rect.x)
+            .set("y", rect.y)
+            .set("width", rect.width)
+            .set("height", rect.height)
+            .set("fill", fill)
+            .set("stroke", "white")
+            .set("stroke-width", "1px")
+            .add(Title::new().add(svg::node::Text::new(escape(&format!(
+                "{path} ({size} bytes)"
+            )))));
+        if highlighted {
+            rectangle = rectangle
+                .set("class", "deletion-candidate")
+                .set("stroke", "#d81b60")
+                .set("stroke-width", "3px");
+        }
+        let mut group = Group::new().add(rectangle);
+
+        // the label is only shown if it fits
+        let label = match is_dir {
+            true => format!("{path} {}", human_size(size)),
+            false => format!("{} {}", self.fs.entry(id).path, human_size(size)),
+        };
+        let fits = label.chars().count() as f64 * CHAR_WIDTH <= rect.width - 2.0 * PADDING
+            && rect.height >= HEADER;
+        if fits {
+            group = group.add(
+                Text::new()
+                    .set("x", rect.x + PADDING)
+                    .set("y", rect.y + FONT_SIZE + PADDING)
+                    .set("font-family", "sans-serif")
+                    .set("font-size", FONT_SIZE)
+                    .add(svg::node::Text::new(escape(&label))),
+            );
+        }
+
+        if !is_dir {
+            return group;
+        }
+        let Some(inside) = rect.shrink(HEADER, PADDING) else {
+            return group;
+        };
+
+        let mut children: Vec<_> = self
+            .fs
+            .children(id)
+            .map(|(_, child)| (child, self.usage[child].size))
+            .filter(|&(_, size)| size > 0)
+            .collect();
+        children.sort_by_key(|&(_, size)| std::cmp::Reverse(size));
+
+        let sizes: Vec<u64> = children.iter().map(|&(_, size)| size).collect();
+        for ((child, _), child_rect) in children.iter().zip(squarify(&sizes, inside)) {
+            group = group.add(self.entry(child, child_rect, depth + 1));
+        }
+        group
+    }
+}
+
+// Draws the filesystem as a squarified treemap: every entry is a rectangle
+// with an area proportional to its total size, and what's in a directory is
+// drawn inside it, under its label. Empty files and directories take no
+// room so they're left out, and so is what's in a directory that's too small
+// to hold it.
+pub fn treemap(
+    fs: &Filesystem,
+    usage: &HashMap<NodeId, Usage>,
+    options: &TreemapOptions,
+) -> String {
+    let treemap = Treemap { fs, usage, options };
+    let rect = Rect {
+        x: 0.0,
+        y: 0.0,
+        width: options.width,
+        height: options.height,
+    };
+
+    Document::new()
+        .set("viewBox", (0, 0, options.width, options.height))
+        .set("width", options.width)
+        .set("height", options.height)
+        .add(treemap.entry(fs.root(), rect, 0))
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{squarify, treemap, Rect, TreemapOptions};
+    use crate::interpret::{interpret, Strictness};
+    use test_case::test_case;
+
+    const EPSILON: f64 = 1e-9;
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn test_squarify_paper_example() {
+        let rects = squarify(&[6, 6, 4, 3, 2, 2, 1], rect(0.0, 0.0, 6.0, 4.0));
+
+        // the first two fill a column, the next two a row above the rest
+        assert_eq!(rect(0.0, 0.0, 3.0, 2.0), rects[0]);
+        assert_eq!(rect(0.0, 2.0, 3.0, 2.0), rects[1]);
+        assert!((rects[2].height - 7.0 / 3.0).abs() < EPSILON);
+        assert!((rects[3].y - rects[2].y).abs() < EPSILON);
+    }
+
+    #[test_case(&[6, 6, 4, 3, 2, 2, 1], rect(0.0, 0.0, 6.0, 4.0))]
+    #[test_case(&[24933642, 14848514, 8504156, 94853], rect(10.0, 20.0, 1200.0, 800.0))]
+    #[test_case(&[1], rect(0.0, 0.0, 3.0, 5.0))]
+    #[test_case(&[100, 1, 1, 1, 1, 1], rect(0.0, 0.0, 1.0, 100.0))]
+    fn test_squarify(sizes: &[u64], outer: Rect) {
+        let rects = squarify(sizes, outer);
+        let total: u64 = sizes.iter().sum();
+
+        assert_eq!(sizes.len(), rects.len());
+        for (i, (&size, r)) in sizes.iter().zip(&rects).enumerate() {
+            // proportional area, inside the outer rectangle
+            let expected = size as f64 / total as f64 * outer.area();
+            assert!((r.area() - expected).abs() < 1e-6 * outer.area());
+            assert!(r.x >= outer.x - EPSILON && r.y >= outer.y - EPSILON);
+            assert!(r.x + r.width <= outer.x + outer.width + 1e-6);
+            assert!(r.y + r.height <= outer.y + outer.height + 1e-6);
+
+            // and not overlapping any other
+            for other in &rects[i + 1..] {
+                let overlap_x = (r.x + r.width).min(other.x + other.width) - r.x.max(other.x);
+                let overlap_y = (r.y + r.height).min(other.y + other.height) - r.y.max(other.y);
+                assert!(overlap_x <= 1e-6 || overlap_y <= 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_treemap() {
+        let fs = interpret(include_str!("../input_small.txt"), Strictness::Stop)
+            .unwrap()
+            .fs;
+        let options = TreemapOptions {
+            highlight: vec!["/d".into()],
+            ..Default::default()
+        };
+
+        let svg = treemap(&fs, &fs.usage(), &options);
+
+        for title in [
+            "/ (48381165 bytes)",
+            "/d (24933642 bytes)",
+            "/a (94853 bytes)",
+        ] {
+            assert!(svg.contains(title), "{title}");
+        }
+        assert_eq!(1, svg.matches("deletion-candidate").count());
+        assert!(svg.contains("\n/d 24M\n"));
+        // `/a/e` is too small to have room for what's in it
+        assert!(svg.contains("/a/e (584 bytes)"));
+        assert!(!svg.contains("/a/e/i"));
+    }
+
+    #[test]
+    fn test_names_are_escaped() {
+        let input = "$ cd /\n$ ls\n100 <b>&.txt";
+        let fs = interpret(input, Strictness::Stop).unwrap().fs;
+
+        let svg = treemap(&fs, &fs.usage(), &TreemapOptions::default());
+
+        assert!(svg.contains("/&lt;b&gt;&amp;.txt (100 bytes)"));
+        assert!(!svg.contains("<b>"));
+    }
+}
@@ -0,0 +1,71 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use day_08::visibility::{count_visible, visibility_grid};
+use day_08::{convert_input_lines, is_tree_visible};
+
+// xorshift, keeps the generated forest the same from run to run
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+fn generate(size: usize) -> Vec<Vec<u8>> {
+    let mut rng = Rng(0x2022_0008);
+    (0..size)
+        .map(|_| (0..size).map(|_| (rng.next() % 10) as u8).collect())
+        .collect()
+}
+
+// what `main` did before, checking every tree inside the border
+fn count_with_is_tree_visible(heights: &[Vec<u8>]) -> usize {
+    let (n_rows, n_cols) = (heights.len(), heights[0].len());
+    let mut n_visible = 2 * n_cols + 2 * n_rows - 4;
+    for y in 1..n_rows - 1 {
+        for x in 1..n_cols - 1 {
+            if is_tree_visible(heights, x, y) {
+                n_visible += 1;
+            }
+        }
+    }
+    n_visible
+}
+
+fn bench_visibility(c: &mut Criterion) {
+    let input = convert_input_lines(&include_str!("../input.txt").lines().collect());
+
+    let mut group = c.benchmark_group("visibility");
+    group.sample_size(20);
+
+    for (name, heights) in [
+        ("input".to_string(), input),
+        ("generated 300x300".to_string(), generate(300)),
+        ("generated 1000x1000".to_string(), generate(1000)),
+    ] {
+        // the benchmarks only mean something if both agree
+        assert_eq!(
+            count_with_is_tree_visible(&heights),
+            count_visible(&visibility_grid(&heights))
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("is_tree_visible", &name),
+            &heights,
+            |b, heights| b.iter(|| count_with_is_tree_visible(heights)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("visibility_grid", &name),
+            &heights,
+            |b, heights| b.iter(|| count_visible(&visibility_grid(heights))),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_visibility);
+criterion_main!(benches);
@@ -0,0 +1,355 @@
+use std::str::FromStr;
+
+use crate::scenic::{viewing_distances, ViewingDistances};
+use crate::visibility::visibility_grid;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    // one digit per tree, like the puzzle input: `30373`
+    Digits,
+    // heights of any size, separated by whitespace or commas: `3, 0, 12`
+    Delimited,
+}
+
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || c == ','
+}
+
+impl Format {
+    // delimited as soon as there's a delimiter anywhere in a row
+    pub fn detect(input: &str) -> Format {
+        match input.lines().any(|line| line.trim().contains(is_delimiter)) {
+            true => Format::Delimited,
+            false => Format::Digits,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    #[error("`{0}` is not a digit")]
+    NotADigit(char),
+
+    #[error("`{0}` is not a height")]
+    NotAHeight(String),
+
+    #[error("expected {expected} trees like in the first row, found {found}")]
+    Ragged { expected: usize, found: usize },
+
+    #[error("there are no trees")]
+    Empty,
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("{line}:{column}: {kind}")]
+pub struct ParseError {
+    // 1-based, the column counts characters
+    pub line: usize,
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+// everything that's wrong with the grid, one error per line
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("{}", .errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n"))]
+pub struct BadForest {
+    pub errors: Vec<ParseError>,
+}
+
+// (column, what's there), the column a tree or error starts at
+type Located<T> = (usize, T);
+
+fn parse_row(line: &str, format: Format) -> (Vec<Located<u32>>, Vec<Located<ParseErrorKind>>) {
+    let mut trees = vec![];
+    let mut errors = vec![];
+
+    match format {
+        Format::Digits => {
+            // whitespace around the row is ignored, like in `Format::detect`
+            let row = line.trim();
+            let start = line.chars().take_while(|c| c.is_whitespace()).count() + 1;
+            for (i, c) in row.chars().enumerate() {
+                match c.to_digit(10) {
+                    Some(height) => trees.push((start + i, height)),
+                    None => errors.push((start + i, ParseErrorKind::NotADigit(c))),
+                }
+            }
+        }
+        Format::Delimited => {
+            let chars: Vec<char> = line.chars().collect();
+            let mut i = 0;
+            while i < chars.len() {
+                if is_delimiter(chars[i]) {
+                    i += 1;
+                    continue;
+                }
+
+                let start = i;
+                while i < chars.len() && !is_delimiter(chars[i]) {
+                    i += 1;
+                }
+                let token: String = chars[start..i].iter().collect();
+                match token.parse() {
+                    Ok(height) => trees.push((start + 1, height)),
+                    Err(_) => errors.push((start + 1, ParseErrorKind::NotAHeight(token))),
+                }
+            }
+        }
+    }
+
+    (trees, errors)
+}
+
+// A rectangular grid of tree heights, with at least one tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Forest {
+    heights: Vec<Vec<u32>>,
+}
+
+impl Forest {
+    // Checks every row, and that they're all as long as the first one. Blank
+    // lines at the end are ignored.
+    pub fn parse(input: &str, format: Format) -> Result<Forest, BadForest> {
+        let lines: Vec<&str> = input.lines().collect();
+        let n_lines = lines
+            .iter()
+            .rposition(|line| !line.trim().is_empty())
+            .map_or(0, |last| last + 1);
+
+        let mut heights = vec![];
+        let mut errors = vec![];
+        // counting the trees that didn't parse, so one bad tree in the first
+        // row doesn't make all the others look ragged
+        let mut width = None;
+        for (i, line) in lines[..n_lines].iter().enumerate() {
+            let (trees, row_errors) = parse_row(line, format);
+            let found = trees.len() + row_errors.len();
+            errors.extend(row_errors.into_iter().map(|(column, kind)| ParseError {
+                line: i + 1,
+                column,
+                kind,
+            }));
+
+            let expected = *width.get_or_insert(found);
+            if found != expected {
+                errors.push(ParseError {
+                    line: i + 1,
+                    column: line.chars().count() + 1,
+                    kind: ParseErrorKind::Ragged { expected, found },
+                });
+            }
+            heights.push(trees.into_iter().map(|(_, height)| height).collect());
+        }
+
+        // no lines, or a first row with only delimiters in it
+        if width.unwrap_or(0) == 0 {
+            errors.push(ParseError {
+                line: 1,
+                column: 1,
+                kind: ParseErrorKind::Empty,
+            });
+        }
+
+        match errors.is_empty() {
+            true => Ok(Forest { heights }),
+            false => Err(BadForest { errors }),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.heights[0].len()
+    }
+
+    pub fn height(&self) -> usize {
+        self.heights.len()
+    }
+
+    pub fn rows(&self) -> &[Vec<u32>] {
+        &self.heights
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<u32> {
+        self.heights.get(y)?.get(x).copied()
+    }
+
+    pub fn visibility(&self) -> Vec<Vec<bool>> {
+        visibility_grid(&self.heights)
+    }
+
+    pub fn viewing_distances(&self) -> Vec<Vec<ViewingDistances>> {
+        viewing_distances(&self.heights)
+    }
+
+    // The viewing distances of one tree, walking away from it. On the
+    // border, the distance towards the edge is 0. `None` outside the grid.
+    pub fn viewing_distances_at(&self, x: usize, y: usize) -> Option<ViewingDistances> {
+        let height = self.get(x, y)?;
+        let distance = |trees: &mut dyn Iterator<Item = u32>| {
+            let mut distance = 0;
+            for tree in trees {
+                distance += 1;
+                if tree >= height {
+                    break;
+                }
+            }
+            distance
+        };
+
+        let row = &self.heights[y];
+        Some(ViewingDistances {
+            north: distance(&mut (0..y).rev().map(|y| self.heights[y][x])),
+            east: distance(&mut row[x + 1..].iter().copied()),
+            south: distance(&mut self.heights[y + 1..].iter().map(|row| row[x])),
+            west: distance(&mut row[..x].iter().rev().copied()),
+        })
+    }
+
+    // 0 on the border, `None` outside the grid
+    pub fn scenic_score(&self, x: usize, y: usize) -> Option<usize> {
+        self.viewing_distances_at(x, y)
+            .map(|distances| distances.scenic_score())
+    }
+}
+
+impl FromStr for Forest {
+    type Err = BadForest;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Forest::parse(s, Format::detect(s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BadForest, Forest, Format, ParseError, ParseErrorKind};
+    use crate::convert_input_lines;
+    use crate::scenic::max_scenic_score;
+    use test_case::test_case;
+
+    const SAMPLE: &str = "30373\n25512\n65332\n33549\n35390\n";
+
+    fn error(line: usize, column: usize, kind: ParseErrorKind) -> ParseError {
+        ParseError { line, column, kind }
+    }
+
+    #[test]
+    fn test_digits() {
+        let forest: Forest = SAMPLE.parse().unwrap();
+
+        assert_eq!((5, 5), (forest.width(), forest.height()));
+        let lines: Vec<&str> = SAMPLE.lines().collect();
+        let expected = convert_input_lines(&lines);
+        for (y, row) in expected.iter().enumerate() {
+            for (x, &height) in row.iter().enumerate() {
+                assert_eq!(Some(height as u32), forest.get(x, y));
+            }
+        }
+        assert_eq!(None, forest.get(5, 0));
+    }
+
+    #[test_case("3 0 3 7 3\n2 5 5 1 2\n6 5 3 3 2\n3 3 5 4 9\n3 5 3 9 0"; "spaces")]
+    #[test_case("3,0,3,7,3\n2,5,5,1,2\n6,5,3,3,2\n3,3,5,4,9\n3,5,3,9,0\n\n"; "commas")]
+    #[test_case("30373\r\n25512\r\n65332\r\n33549\r\n35390\r\n"; "crlf")]
+    #[test_case("30373 \n25512\t\n 65332\n33549\n35390  \n"; "whitespace around rows")]
+    fn test_same_as_sample(input: &str) {
+        assert_eq!(SAMPLE.parse::<Forest>(), input.parse());
+    }
+
+    #[test]
+    fn test_multi_digit_heights() {
+        let forest: Forest = "10, 200, 10\n300 1000 300\n10 ,200, 10".parse().unwrap();
+
+        assert_eq!(Some(1000), forest.get(1, 1));
+        assert!(forest.visibility()[1][1]);
+        assert_eq!(1, forest.scenic_score(1, 1).unwrap());
+    }
+
+    #[test_case("30373\n25a12\n65332", Format::Digits, &[error(2, 3, ParseErrorKind::NotADigit('a'))])]
+    #[test_case(" 30373\n  2a512", Format::Digits, &[error(2, 4, ParseErrorKind::NotADigit('a'))]; "column after leading whitespace")]
+    #[test_case("30373\n2551\n653321", Format::Digits, &[
+        error(2, 5, ParseErrorKind::Ragged { expected: 5, found: 4 }),
+        error(3, 7, ParseErrorKind::Ragged { expected: 5, found: 6 }),
+    ])]
+    #[test_case("3 0 3\n2 -5 x\n", Format::Delimited, &[
+        error(2, 3, ParseErrorKind::NotAHeight("-5".to_string())),
+        error(2, 6, ParseErrorKind::NotAHeight("x".to_string())),
+    ])]
+    #[test_case("1 2\n\n3 4", Format::Delimited, &[error(2, 1, ParseErrorKind::Ragged { expected: 2, found: 0 })])]
+    #[test_case("3x3\n333\n333", Format::Digits, &[error(1, 2, ParseErrorKind::NotADigit('x'))]; "bad tree in the first row")]
+    #[test_case("\n\n", Format::Digits, &[error(1, 1, ParseErrorKind::Empty)])]
+    #[test_case(", ,\n", Format::Delimited, &[error(1, 1, ParseErrorKind::Empty)]; "only delimiters")]
+    fn test_errors(input: &str, format: Format, expected: &[ParseError]) {
+        assert_eq!(expected, Forest::parse(input, format).unwrap_err().errors);
+    }
+
+    // the other rows are as long as the first one, counting its bad tree
+    #[test_case("3x3\n333\n333", Format::Digits; "digits")]
+    #[test_case("3 x 3\n3 3 3\n3 3 3", Format::Delimited; "delimited")]
+    #[test_case("3,-1\n3,3\n3,3", Format::Delimited; "negative")]
+    fn test_bad_tree_in_the_first_row(input: &str, format: Format) {
+        let errors = Forest::parse(input, format).unwrap_err().errors;
+
+        assert_eq!(1, errors.len(), "{errors:?}");
+        assert_eq!(1, errors[0].line);
+        assert!(!matches!(errors[0].kind, ParseErrorKind::Ragged { .. }));
+    }
+
+    #[test_case(", ,\n")]
+    #[test_case(","; "comma")]
+    #[test_case(" , \n\n"; "comma and blank lines")]
+    fn test_first_row_without_trees(input: &str) {
+        assert_eq!(
+            Err(BadForest {
+                errors: vec![error(1, 1, ParseErrorKind::Empty)]
+            }),
+            input.parse::<Forest>()
+        );
+    }
+
+    #[test_case("30373 \n25512\n65332\n33549\n35390\n"; "trailing space")]
+    #[test_case("30373\t\n 25512\n65332\n33549\n  35390  \n"; "tabs and spaces")]
+    fn test_whitespace_around_digit_rows(input: &str) {
+        assert_eq!(Format::Digits, Format::detect(input));
+        assert_eq!(
+            SAMPLE.parse::<Forest>(),
+            Forest::parse(input, Format::Digits)
+        );
+    }
+
+    #[test]
+    fn test_error_message() {
+        let e = "303\n2x5\n65".parse::<Forest>().unwrap_err();
+
+        assert_eq!(
+            "2:2: `x` is not a digit\n3:3: expected 3 trees like in the first row, found 2",
+            e.to_string()
+        );
+    }
+
+    #[test]
+    fn test_border_scores() {
+        let forest: Forest = SAMPLE.parse().unwrap();
+
+        for (x, y) in [(0, 0), (4, 0), (0, 2), (4, 4), (2, 4)] {
+            assert_eq!(Some(0), forest.scenic_score(x, y), "({x}, {y})");
+        }
+        assert_eq!(Some(8), forest.scenic_score(2, 3));
+        assert_eq!(None, forest.scenic_score(5, 5));
+    }
+
+    #[test]
+    fn test_input() {
+        let forest: Forest = include_str!("../input.txt").parse().unwrap();
+        let distances = forest.viewing_distances();
+
+        for (y, row) in distances.iter().enumerate() {
+            for (x, &distances) in row.iter().enumerate() {
+                assert_eq!(Some(distances), forest.viewing_distances_at(x, y));
+            }
+        }
+        assert_eq!(
+            Some(536625),
+            max_scenic_score(&distances).map(|(_, score)| score)
+        );
+    }
+}
@@ -0,0 +1,142 @@
+// How far can be seen from every tree, in each direction, up to the first
+// tree that's at least as tall or the edge of the grid.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ViewingDistances {
+    pub north: usize,
+    pub east: usize,
+    pub south: usize,
+    pub west: usize,
+}
+
+impl ViewingDistances {
+    // 0 on the border, nothing can be seen past the edge
+    pub fn scenic_score(&self) -> usize {
+        self.north * self.east * self.south * self.west
+    }
+}
+
+// The viewing distances of every tree, in O(n^2).
+//
+// Going along a row or column, a stack keeps the trees that could still
+// block the view of the next ones: when a tree comes, the shorter trees on
+// top of the stack are popped, since it hides them from everything after
+// it. What is left on top is the tree that blocks its view. The stack only
+// ever holds trees from the tallest to the shortest, and every tree is
+// pushed and popped once per direction.
+pub fn viewing_distances<T: Ord + Copy>(heights: &[Vec<T>]) -> Vec<Vec<ViewingDistances>> {
+    let n_rows = heights.len();
+    let n_cols = heights.first().map_or(0, |row| row.len());
+    let mut distances = vec![vec![ViewingDistances::default(); n_cols]; n_rows];
+
+    // `cells` go away from the edge the view is towards
+    let mut sweep = |cells: &mut dyn Iterator<Item = (usize, usize)>,
+                     distance: fn(&mut ViewingDistances) -> &mut usize| {
+        // (index along the line, height)
+        let mut stack: Vec<(usize, T)> = vec![];
+        for (i, (x, y)) in cells.enumerate() {
+            let height = heights[y][x];
+            while matches!(stack.last(), Some(&(_, blocking)) if blocking < height) {
+                stack.pop();
+            }
+
+            *distance(&mut distances[y][x]) = match stack.last() {
+                Some(&(blocking, _)) => i - blocking,
+                // all the way to the edge
+                None => i,
+            };
+            stack.push((i, height));
+        }
+    };
+
+    for y in 0..n_rows {
+        sweep(&mut (0..n_cols).map(|x| (x, y)), |d| &mut d.west);
+        sweep(&mut (0..n_cols).rev().map(|x| (x, y)), |d| &mut d.east);
+    }
+    for x in 0..n_cols {
+        sweep(&mut (0..n_rows).map(|y| (x, y)), |d| &mut d.north);
+        sweep(&mut (0..n_rows).rev().map(|y| (x, y)), |d| &mut d.south);
+    }
+
+    distances
+}
+
+// the highest scenic score and where it is, `None` for an empty grid
+pub fn max_scenic_score(distances: &[Vec<ViewingDistances>]) -> Option<((usize, usize), usize)> {
+    distances
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .map(move |(x, distances)| ((x, y), distances.scenic_score()))
+        })
+        .max_by_key(|&(_, score)| score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{max_scenic_score, viewing_distances, ViewingDistances};
+    use crate::{calc_scenic_score, convert_input_lines};
+
+    const SAMPLE: [&str; 5] = ["30373", "25512", "65332", "33549", "35390"];
+
+    #[test]
+    fn test_directions() {
+        let heights = convert_input_lines(&SAMPLE.to_vec());
+
+        let distances = viewing_distances(&heights);
+
+        // the examples from the puzzle
+        assert_eq!(
+            ViewingDistances {
+                north: 1,
+                east: 2,
+                south: 2,
+                west: 1
+            },
+            distances[1][2]
+        );
+        assert_eq!(
+            ViewingDistances {
+                north: 2,
+                east: 2,
+                south: 1,
+                west: 2
+            },
+            distances[3][2]
+        );
+        // on the border, the view ends at the edge right away
+        assert_eq!(
+            ViewingDistances {
+                north: 0,
+                east: 2,
+                south: 2,
+                west: 0
+            },
+            distances[0][0]
+        );
+        assert_eq!(Some(((2, 3), 8)), max_scenic_score(&distances));
+    }
+
+    #[test]
+    fn test_input() {
+        let heights = convert_input_lines(&include_str!("../input.txt").lines().collect());
+
+        let distances = viewing_distances(&heights);
+
+        for (y, row) in distances.iter().enumerate() {
+            for (x, distances) in row.iter().enumerate() {
+                assert_eq!(
+                    calc_scenic_score(&heights, x, y),
+                    distances.scenic_score(),
+                    "({x}, {y})"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(None, max_scenic_score(&viewing_distances::<u8>(&[])));
+    }
+}
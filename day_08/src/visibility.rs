@@ -0,0 +1,121 @@
+// Which trees can be seen from outside the grid, for every tree at once.
+//
+// Sweeping a row or column from one edge while keeping the tallest tree so
+// far tells for each tree whether it can be seen from that edge. Four
+// sweeps, one from each edge, visit every tree four times, so the grid takes
+// O(n^2) instead of the O(n^3) of calling `is_tree_visible` for every tree.
+//
+// Trees on the border are always visible, even with a height of 0.
+pub fn visibility_grid<T: Ord + Copy>(heights: &[Vec<T>]) -> Vec<Vec<bool>> {
+    let n_rows = heights.len();
+    let n_cols = heights.first().map_or(0, |row| row.len());
+    let mut visible = vec![vec![false; n_cols]; n_rows];
+
+    // the tallest tree so far is `None` at the edge, where nothing is in
+    // the way
+    let mut sweep = |cells: &mut dyn Iterator<Item = (usize, usize)>| {
+        let mut tallest: Option<T> = None;
+        for (x, y) in cells {
+            let height = heights[y][x];
+            if !matches!(tallest, Some(tallest) if height <= tallest) {
+                visible[y][x] = true;
+                tallest = Some(height);
+            }
+        }
+    };
+
+    for y in 0..n_rows {
+        // from the West and from the East
+        sweep(&mut (0..n_cols).map(|x| (x, y)));
+        sweep(&mut (0..n_cols).rev().map(|x| (x, y)));
+    }
+    for x in 0..n_cols {
+        // from the North and from the South
+        sweep(&mut (0..n_rows).map(|y| (x, y)));
+        sweep(&mut (0..n_rows).rev().map(|y| (x, y)));
+    }
+
+    visible
+}
+
+pub fn count_visible(visible: &[Vec<bool>]) -> usize {
+    visible.iter().flatten().filter(|&&visible| visible).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{count_visible, visibility_grid};
+    use crate::{convert_input_lines, is_tree_visible};
+    use test_case::test_case;
+
+    // xorshift, the same forest every time
+    fn generate(size: usize, max_height: u8, seed: u64) -> Vec<Vec<u8>> {
+        let mut state = seed;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % (max_height as u64 + 1)) as u8
+        };
+
+        (0..size)
+            .map(|_| (0..size).map(|_| next()).collect())
+            .collect()
+    }
+
+    fn check_against_is_tree_visible(heights: &[Vec<u8>]) {
+        let visible = visibility_grid(heights);
+
+        for (y, row) in visible.iter().enumerate() {
+            for (x, &visible) in row.iter().enumerate() {
+                assert_eq!(is_tree_visible(heights, x, y), visible, "({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample() {
+        let heights = convert_input_lines(&vec!["30373", "25512", "65332", "33549", "35390"]);
+
+        let visible = visibility_grid(&heights);
+
+        let expected = [
+            "#####", //
+            "###.#", //
+            "##.##", //
+            "#.#.#", //
+            "#####",
+        ];
+        let visible: Vec<String> = visible
+            .iter()
+            .map(|row| row.iter().map(|&v| if v { '#' } else { '.' }).collect())
+            .collect();
+        assert_eq!(expected.to_vec(), visible);
+        assert_eq!(21, count_visible(&visibility_grid(&heights)));
+    }
+
+    #[test]
+    fn test_input() {
+        let input = include_str!("../input.txt");
+        let heights = convert_input_lines(&input.lines().collect());
+
+        check_against_is_tree_visible(&heights);
+    }
+
+    #[test_case(10, 9, 1)]
+    #[test_case(50, 9, 2)]
+    #[test_case(50, 2, 3; "few heights")]
+    #[test_case(30, 0, 4; "all the same height")]
+    fn test_generated(size: usize, max_height: u8, seed: u64) {
+        check_against_is_tree_visible(&generate(size, max_height, seed));
+    }
+
+    #[test_case(&[]; "empty")]
+    #[test_case(&[vec![0]]; "one tree")]
+    #[test_case(&[vec![0, 0, 0]]; "one row")]
+    fn test_small(heights: &[Vec<u8>]) {
+        let visible = visibility_grid(heights);
+
+        assert_eq!(heights.iter().flatten().count(), count_visible(&visible));
+    }
+}